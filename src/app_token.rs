@@ -0,0 +1,590 @@
+//! App access tokens from the client credentials flow
+
+#[cfg(feature = "client")]
+use crate::client::TwitchOAuthClient;
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "client")]
+use crate::error::{read_json, TwitchOAuthError};
+#[cfg(feature = "client")]
+use crate::scope::{join_scopes, Scope};
+use crate::token::{
+    bearer_header, deserialize_bearer, expires_at, expires_in_duration, expires_within,
+    helix_headers, DebugWith, Secret, DEFAULT_EXPIRY_SKEW,
+};
+#[cfg(feature = "client")]
+use crate::validate::ValidatedToken;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "client")]
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "client")]
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "client")]
+use url::Url;
+
+/// The app access token returned by the client credentials flow
+///
+/// ```rust
+/// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+///     r#"{"access_token":"token","expires_in":60,"scope":["chat:read"],"token_type":"bearer"}"#,
+/// ).unwrap();
+/// let display = token.to_string();
+/// assert!(display.contains("access_token: token"));
+/// assert!(display.contains("expires_in: 60"));
+/// assert!(display.contains("scope: Some([\"chat:read\"])"));
+/// assert!(display.contains("token_type: bearer"));
+/// ```
+///
+/// A token without scopes serializes without a `scope` field and round-trips:
+///
+/// ```rust
+/// use twitch_oauth_async_std::AppAccessToken;
+///
+/// let json = r#"{"access_token":"token","expires_in":60,"token_type":"bearer"}"#;
+/// let token: AppAccessToken = serde_json::from_str(json).unwrap();
+/// assert_eq!(serde_json::to_string(&token).unwrap(), json);
+/// assert_eq!(serde_json::from_str::<AppAccessToken>(json).unwrap(), token);
+/// ```
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct AppAccessToken {
+    pub access_token: String,
+    pub expires_in: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_bearer")]
+    pub token_type: String,
+    /// When the token was obtained, set when the response is received
+    ///
+    /// It isn't serialized, so a deserialized token counts as obtained at the Unix epoch and
+    /// expired. Persist a [`StoredToken`](crate::StoredToken) to keep the expiry of a token.
+    #[serde(skip, default = "obtained_at_unknown")]
+    pub obtained_at: SystemTime,
+}
+
+fn obtained_at_unknown() -> SystemTime {
+    SystemTime::UNIX_EPOCH
+}
+
+impl AppAccessToken {
+    /// When the token expires
+    pub fn expires_at(&self) -> SystemTime {
+        expires_at(self.obtained_at, self.expires_in)
+    }
+
+    /// The `Authorization` header value for this token
+    ///
+    /// ```rust
+    /// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","expires_in":3600,"scope":null,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert_eq!(token.bearer_header(), "Bearer a");
+    ///
+    /// let err = serde_json::from_str::<twitch_oauth_async_std::AppAccessToken>(
+    ///     r#"{"access_token":"a","expires_in":3600,"scope":null,"token_type":"mac"}"#,
+    /// ).unwrap_err();
+    /// assert!(err.to_string().contains("expected bearer token_type, got mac"));
+    /// ```
+    pub fn bearer_header(&self) -> String {
+        bearer_header(&self.access_token)
+    }
+
+    /// The `Client-Id` and `Authorization` headers Helix expects with this token
+    ///
+    /// ```rust
+    /// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","expires_in":3600,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     token.helix_headers("client_id"),
+    ///     vec![
+    ///         ("Client-Id".to_string(), "client_id".to_string()),
+    ///         ("Authorization".to_string(), "Bearer a".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn helix_headers(&self, client_id: &str) -> Vec<(String, String)> {
+        helix_headers(&self.access_token, client_id)
+    }
+
+    /// The granted scopes, empty when Twitch sent none
+    ///
+    /// ```rust
+    /// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","expires_in":3600,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert!(token.scope.is_none());
+    /// assert!(token.scopes().is_empty());
+    /// ```
+    pub fn scopes(&self) -> &[String] {
+        self.scope.as_deref().unwrap_or_default()
+    }
+
+    /// How long the token was valid for when obtained
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","expires_in":3600,"scope":null,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert_eq!(token.expires_in_duration(), Duration::from_secs(3600));
+    /// ```
+    pub fn expires_in_duration(&self) -> Duration {
+        expires_in_duration(self.expires_in)
+    }
+
+    /// Whether the token is expired, treating it as expired 30 seconds early to allow for clock skew
+    ///
+    /// The skew is always the default one, the token doesn't know the
+    /// [`with_expiry_skew`](crate::TwitchOAuthClient::with_expiry_skew) of the client that
+    /// obtained it. Use [`expires_within_skewed`](Self::expires_within_skewed) for another skew.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_with(&SystemClock)
+    }
+
+    /// Whether the token expires within `duration`, allowing for 30 seconds of clock skew
+    ///
+    /// Like [`is_expired`](Self::is_expired), the skew is always the default one.
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        self.expires_within_with(&SystemClock, duration)
+    }
+
+    /// Whether the token is expired, against `clock` and allowing for 30 seconds of clock skew
+    pub fn is_expired_with(&self, clock: &dyn Clock) -> bool {
+        self.expires_within_with(clock, Duration::from_secs(0))
+    }
+
+    /// Whether the token expires within `duration`, against `clock` and allowing for 30 seconds of clock skew
+    pub fn expires_within_with(&self, clock: &dyn Clock, duration: Duration) -> bool {
+        self.expires_within_skewed(clock, duration, DEFAULT_EXPIRY_SKEW)
+    }
+
+    /// Whether the token expires within `duration`, against `clock` and treating it as expired `skew` early
+    ///
+    /// The only expiry check taking a skew, e.g. the one configured on a client, passed along with
+    /// its clock as `expires_within_skewed(client.clock(), duration, client.expiry_skew())`.
+    ///
+    /// ```rust
+    /// use std::time::{Duration, SystemTime};
+    /// use twitch_oauth_async_std::{MockClock, AppAccessToken};
+    ///
+    /// let clock = MockClock::new(SystemTime::now());
+    /// let mut token: AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","expires_in":3600,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// token.obtained_at = clock.now();
+    /// let no_time = Duration::from_secs(0);
+    /// assert!(!token.expires_within_skewed(&clock, no_time, Duration::from_secs(60)));
+    /// assert!(token.expires_within_skewed(&clock, no_time, Duration::from_secs(3600)));
+    /// ```
+    pub fn expires_within_skewed(
+        &self,
+        clock: &dyn Clock,
+        duration: Duration,
+        skew: Duration,
+    ) -> bool {
+        expires_within(self.expires_at(), clock.now(), duration, skew)
+    }
+}
+
+impl AppAccessToken {
+    /// The `Debug` output with the access token in full, which `{:?}` redacts
+    ///
+    /// ```rust
+    /// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"twitchtoken","expires_in":60,"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert!(format!("{:?}", token).contains(r#"access_token: "tw...redacted""#));
+    /// assert!(!format!("{:?}", token).contains("twitchtoken"));
+    /// assert!(token.debug_full().contains(r#"access_token: "twitchtoken""#));
+    /// ```
+    pub fn debug_full(&self) -> String {
+        format!("{:?}", DebugWith(|f| self.fmt_debug(f, true)))
+    }
+
+    fn fmt_debug(&self, f: &mut fmt::Formatter, full: bool) -> fmt::Result {
+        f.debug_struct("AppAccessToken")
+            .field(
+                "access_token",
+                &Secret {
+                    value: &self.access_token,
+                    full,
+                },
+            )
+            .field("expires_in", &self.expires_in)
+            .field("scope", &self.scope)
+            .field("token_type", &self.token_type)
+            .field("obtained_at", &self.obtained_at)
+            .finish()
+    }
+}
+
+impl fmt::Debug for AppAccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_debug(f, false)
+    }
+}
+
+// To use the `{}` marker, the trait `fmt::Display` must be implemented
+// manually for the type.
+impl fmt::Display for AppAccessToken {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string1 = format!("access_token: {}\n", self.access_token);
+        let string2 = format!("{} expires_in: {}\n", string1, self.expires_in);
+        let string3 = format!("{} scope: {:?}\n", string2, self.scope);
+        let string4 = format!("{} token_type: {}\n", string3, self.token_type);
+        write!(f, "{}", string4)
+    }
+}
+
+/// To call Helix with surf, you need to provide the request, the app access token and your client_id
+///
+/// Sets the `Authorization` and `Client-Id` headers Helix expects.
+///
+/// ```rust
+/// let token: twitch_oauth_async_std::AppAccessToken = serde_json::from_str(
+///     r#"{"access_token":"a","expires_in":3600,"token_type":"bearer"}"#,
+/// ).unwrap();
+/// let req = twitch_oauth_async_std::authorize_request(
+///     surf::RequestBuilder::new(
+///         surf::http::Method::Get,
+///         surf::Url::parse("https://api.twitch.tv/helix/games/top").unwrap(),
+///     ),
+///     &token,
+///     "client_id",
+/// )
+/// .build();
+/// assert_eq!(req.header("authorization").unwrap().as_str(), "Bearer a");
+/// assert_eq!(req.header("client-id").unwrap().as_str(), "client_id");
+/// ```
+#[cfg(feature = "client")]
+pub fn authorize_request(
+    builder: surf::RequestBuilder,
+    token: &AppAccessToken,
+    client_id: &str,
+) -> surf::RequestBuilder {
+    token
+        .helix_headers(client_id)
+        .into_iter()
+        .fold(builder, |builder, (name, value)| {
+            builder.header(name.as_str(), value)
+        })
+}
+
+/// A fluent app access token request, gathering every option in one call chain
+///
+/// ```rust
+/// use std::time::Duration;
+/// use twitch_oauth_async_std::{AppTokenRequest, Scope};
+///
+/// let token = AppTokenRequest::builder("client_id", "client_secret")
+///     .scopes(&["chat:read".to_string()])
+///     .typed_scopes(&[Scope::ChatEdit])
+///     .timeout(Duration::from_secs(5))
+///     .retries(3, Duration::from_millis(200))
+///     .send();
+/// ```
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct AppTokenRequest {
+    client: TwitchOAuthClient,
+    scopes: Vec<String>,
+    params: HashMap<String, String>,
+}
+
+#[cfg(feature = "client")]
+impl AppTokenRequest {
+    /// To request a token, you need to provide your client_id and client_secret
+    pub fn builder(client_id: &str, client_secret: &str) -> AppTokenRequest {
+        AppTokenRequest::with_client(TwitchOAuthClient::new(client_id, client_secret))
+    }
+
+    /// Send the request with an existing client, keeping its configuration
+    pub fn with_client(client: TwitchOAuthClient) -> AppTokenRequest {
+        AppTokenRequest {
+            client,
+            scopes: Vec::new(),
+            params: HashMap::new(),
+        }
+    }
+
+    /// Request `scopes`, added to the ones already requested
+    pub fn scopes(mut self, scopes: &[String]) -> AppTokenRequest {
+        self.scopes.extend_from_slice(scopes);
+        self
+    }
+
+    /// Request typed `scopes`, added to the ones already requested
+    pub fn typed_scopes(mut self, scopes: &[Scope]) -> AppTokenRequest {
+        self.scopes
+            .extend(scopes.iter().map(|scope| scope.as_str().to_string()));
+        self
+    }
+
+    /// Send an extra form field, see [`TwitchOAuthClient::app_access_token_with_params`]
+    pub fn param(mut self, key: &str, value: &str) -> AppTokenRequest {
+        self.params.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// See [`TwitchOAuthClient::with_timeout`]
+    pub fn timeout(mut self, timeout: Duration) -> AppTokenRequest {
+        self.client = self.client.with_timeout(timeout);
+        self
+    }
+
+    /// See [`TwitchOAuthClient::with_retries`]
+    pub fn retries(mut self, max: u32, base_delay: Duration) -> AppTokenRequest {
+        self.client = self.client.with_retries(max, base_delay);
+        self
+    }
+
+    /// See [`TwitchOAuthClient::with_base_url`]
+    pub fn base_url(mut self, base_url: &str) -> AppTokenRequest {
+        self.client = self.client.with_base_url(base_url);
+        self
+    }
+
+    /// Retrieve the app access token
+    pub async fn send(self) -> Result<AppAccessToken, TwitchOAuthError> {
+        let joinee_scopes = join_scopes(&self.scopes);
+
+        let mut extra: HashMap<&str, &str> = HashMap::new();
+        if !joinee_scopes.is_empty() {
+            extra.insert("scope", joinee_scopes.as_str());
+        }
+        extra.extend(
+            self.params
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        self.client.app_access_token_with_params(&extra).await
+    }
+}
+
+/// Caches an app access token, fetching a new one when it expires within 5 minutes
+///
+/// Concurrent callers share a single fetch: while one task refreshes the token, the others wait
+/// for it and get the same token. Dropping a call never leaves a partial token in the cache.
+///
+/// ```rust
+/// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+/// let cache = std::sync::Arc::new(twitch_oauth_async_std::AppTokenCache::new(client));
+/// let token = cache.token();
+///
+/// fn assert_send_sync<T: Send + Sync>(_: &T) {}
+/// fn assert_send<T: Send>(_: &T) {}
+/// assert_send_sync(&cache);
+/// assert_send(&token);
+/// ```
+#[cfg(feature = "client")]
+#[derive(Debug)]
+pub struct AppTokenCache {
+    client: TwitchOAuthClient,
+    token: Mutex<Option<AppAccessToken>>,
+    // Held while fetching, so only one fetch runs at a time
+    refresh: futures_util::lock::Mutex<()>,
+}
+
+#[cfg(feature = "client")]
+impl AppTokenCache {
+    /// To create a cache, you need to provide the client used to fetch tokens
+    pub fn new(client: TwitchOAuthClient) -> AppTokenCache {
+        AppTokenCache {
+            client,
+            token: Mutex::new(None),
+            refresh: futures_util::lock::Mutex::new(()),
+        }
+    }
+
+    /// The cached token, or a new one if it is missing or expires within 5 minutes
+    pub async fn token(&self) -> Result<AppAccessToken, TwitchOAuthError> {
+        if let Some(token) = self.cached() {
+            return Ok(token);
+        }
+
+        let _refresh = self.refresh.lock().await;
+        // Another caller may have fetched a token while we waited
+        if let Some(token) = self.cached() {
+            return Ok(token);
+        }
+
+        let token = self.client.app_access_token().await?;
+        *self.token.lock().unwrap() = Some(token.clone());
+
+        Ok(token)
+    }
+
+    fn cached(&self) -> Option<AppAccessToken> {
+        self.token
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|token| {
+                !token.expires_within_skewed(
+                    self.client.clock(),
+                    Duration::from_secs(5 * 60),
+                    self.client.expiry_skew(),
+                )
+            })
+            .cloned()
+    }
+}
+
+#[cfg(feature = "client")]
+impl TwitchOAuthClient {
+    /// Retrieve an app access token
+    pub async fn app_access_token(&self) -> Result<AppAccessToken, TwitchOAuthError> {
+        self.app_access_token_with_params(&HashMap::new()).await
+    }
+
+    /// Retrieve an app access token with a scope array
+    pub async fn app_access_token_with_scopes(
+        &self,
+        scopes: Vec<String>,
+    ) -> Result<AppAccessToken, TwitchOAuthError> {
+        let joinee_scopes = join_scopes(&scopes);
+
+        let mut extra = HashMap::new();
+        extra.insert("scope", joinee_scopes.as_str());
+        self.app_access_token_with_params(&extra).await
+    }
+
+    /// Retrieve an app access token with extra form fields, merged over the default ones
+    pub async fn app_access_token_with_params(
+        &self,
+        extra: &HashMap<&str, &str>,
+    ) -> Result<AppAccessToken, TwitchOAuthError> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "client_credentials");
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("client_secret", self.client_secret.as_str());
+        params.extend(extra.iter().map(|(key, value)| (*key, *value)));
+        let url = Url::parse_with_params(&self.endpoints.token, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let mut res = self.send_with_retries(|| self.client.post(&url)).await?;
+        let mut resp: AppAccessToken = read_json(&mut res).await?;
+        resp.obtained_at = self.clock().now();
+
+        Ok(resp)
+    }
+
+    /// Retrieve an app access token with a scope array, then validate it to see the scopes actually granted
+    ///
+    /// Twitch may grant fewer scopes than requested, compare them with [`ValidatedToken::scope_diff`].
+    pub async fn app_access_token_validated(
+        &self,
+        scopes: Vec<String>,
+    ) -> Result<(AppAccessToken, ValidatedToken), TwitchOAuthError> {
+        let token = if scopes.is_empty() {
+            self.app_access_token().await?
+        } else {
+            self.app_access_token_with_scopes(scopes).await?
+        };
+        let validated = self.validate(&token.access_token).await?;
+
+        Ok((token, validated))
+    }
+
+    /// Retrieve an app access token with typed scopes
+    pub async fn app_access_token_with_typed_scopes(
+        &self,
+        scopes: &[Scope],
+    ) -> Result<AppAccessToken, TwitchOAuthError> {
+        let scopes = scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect();
+        self.app_access_token_with_scopes(scopes).await
+    }
+}
+
+/// To retrieve a token, you need to provide your client_id and client_secret as well as a scope array
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::get_app_access_token("client_id", "client_secret");
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_app_access_token(
+    client_id: &str,
+    client_secret: &str,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .app_access_token()
+        .await
+}
+
+/// To retrieve a token, you need to provide your client_id and client_secret as well as a scope array
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::get_app_access_token_with_scopes("client_id", "client_secret", vec!["scopes".to_string()]);
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_app_access_token_with_scopes(
+    client_id: &str,
+    client_secret: &str,
+    scopes: Vec<String>,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .app_access_token_with_scopes(scopes)
+        .await
+}
+
+/// To retrieve a token and the scopes it was actually granted, you need to provide your client_id and client_secret as well as a scope array
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::get_app_access_token_validated("client_id", "client_secret", vec!["chat:read".to_string()]);
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_app_access_token_validated(
+    client_id: &str,
+    client_secret: &str,
+    scopes: Vec<String>,
+) -> Result<(AppAccessToken, ValidatedToken), TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .app_access_token_validated(scopes)
+        .await
+}
+
+/// To retrieve a token with extra form fields, you need to provide your client_id, client_secret and the fields
+///
+/// The fields are merged over the default ones, so they can also override them.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// let mut extra = HashMap::new();
+/// extra.insert("scope", "chat:read");
+/// let token = twitch_oauth_async_std::get_app_access_token_with_params("client_id", "client_secret", &extra);
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_app_access_token_with_params(
+    client_id: &str,
+    client_secret: &str,
+    extra: &HashMap<&str, &str>,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .app_access_token_with_params(extra)
+        .await
+}
+
+/// To retrieve a token, you need to provide your client_id and client_secret as well as a typed scope array
+///
+/// ```rust
+/// use twitch_oauth_async_std::Scope;
+///
+/// let token = twitch_oauth_async_std::get_app_access_token_with_typed_scopes("client_id", "client_secret", &[Scope::ChatRead]);
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_app_access_token_with_typed_scopes(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[Scope],
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .app_access_token_with_typed_scopes(scopes)
+        .await
+}
@@ -0,0 +1,572 @@
+//! Authorization urls, PKCE, state and callback parsing
+
+use crate::error::TwitchOAuthError;
+use crate::scope::{join_scopes, split_scopes, Scope, ScopeSet};
+use crate::validate::ValidatedToken;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use subtle::ConstantTimeEq;
+use url::Url;
+
+/// A PKCE code verifier and its S256 code challenge
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub code_verifier: String,
+    pub code_challenge: String,
+}
+
+impl Pkce {
+    /// Generate a random code_verifier and its S256 code_challenge
+    ///
+    /// ```rust
+    /// let pkce = twitch_oauth_async_std::Pkce::generate();
+    /// assert_eq!(pkce.code_verifier.len(), 43);
+    /// ```
+    pub fn generate() -> Pkce {
+        let code_verifier = random_url_safe_string();
+        let code_challenge = base64::encode_config(
+            Sha256::digest(code_verifier.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        );
+
+        Pkce {
+            code_verifier,
+            code_challenge,
+        }
+    }
+}
+
+/// The successful result of an authorization callback
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthCallback {
+    pub code: String,
+    pub scope: Vec<String>,
+    pub state: String,
+}
+
+/// The access token of an implicit grant, parsed from the redirect fragment
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImplicitToken {
+    pub access_token: String,
+    pub scope: Vec<String>,
+    pub state: String,
+    pub token_type: String,
+}
+
+/// Errors returned while parsing an authorization callback
+#[derive(Debug, Clone)]
+pub enum CallbackError {
+    /// Twitch redirected with an error, e.g. `access_denied` when the user denies
+    Denied {
+        error: String,
+        error_description: Option<String>,
+    },
+    /// A required query parameter is missing from the callback
+    MissingParameter(&'static str),
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallbackError::Denied {
+                error,
+                error_description: Some(description),
+            } => write!(f, "{}: {}", error, description),
+            CallbackError::Denied { error, .. } => write!(f, "{}", error),
+            CallbackError::MissingParameter(name) => write!(f, "missing parameter: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for CallbackError {}
+
+fn random_url_safe_string() -> String {
+    random_url_safe_string_from_rng(&mut rand::rngs::OsRng)
+}
+
+fn random_url_safe_string_from_rng(rng: &mut impl RngCore) -> String {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// To protect the authorization callback against CSRF, generate a random state and pass it to the authorize URL
+///
+/// ```rust
+/// let state = twitch_oauth_async_std::generate_state();
+/// assert_eq!(state.len(), 43);
+/// ```
+pub fn generate_state() -> String {
+    random_url_safe_string()
+}
+
+/// To generate a state from a specific RNG, e.g. a seeded one in tests, you need to provide the RNG
+///
+/// Outside of tests, prefer [`generate_state`], which uses the OS RNG.
+///
+/// ```rust
+/// use rand::{rngs::StdRng, SeedableRng};
+///
+/// let state = twitch_oauth_async_std::generate_state_from_rng(&mut StdRng::seed_from_u64(42));
+/// let again = twitch_oauth_async_std::generate_state_from_rng(&mut StdRng::seed_from_u64(42));
+/// assert_eq!(state, again);
+/// assert_eq!(state.len(), 43);
+/// ```
+pub fn generate_state_from_rng(rng: &mut impl RngCore) -> String {
+    random_url_safe_string_from_rng(rng)
+}
+
+/// To verify the state received on the callback, you need to provide the expected state and the received one
+///
+/// The comparison runs in constant time.
+///
+/// ```rust
+/// let state = twitch_oauth_async_std::generate_state();
+/// assert!(twitch_oauth_async_std::verify_state(&state, &state));
+/// assert!(!twitch_oauth_async_std::verify_state(&state, "forged"));
+/// ```
+pub fn verify_state(expected: &str, received: &str) -> bool {
+    expected.as_bytes().ct_eq(received.as_bytes()).into()
+}
+
+const AUTHORIZE_URL: &str = "https://id.twitch.tv/oauth2/authorize";
+
+pub(crate) fn authorize_endpoint() -> Url {
+    Url::parse(AUTHORIZE_URL).expect("AUTHORIZE_URL is a valid url")
+}
+
+// Twitch only redirects to absolute http(s) urls, and only says so after the user logged in
+pub(crate) fn check_redirect_uri(redirect_uri: &str) -> Result<(), TwitchOAuthError> {
+    match Url::parse(redirect_uri) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => Ok(()),
+        _ => Err(TwitchOAuthError::InvalidRedirectUri(
+            redirect_uri.to_string(),
+        )),
+    }
+}
+
+/// To start the authorization code flow, you need to provide your client_id, redirect_uri, a scope array and a state
+///
+/// Fails with [`TwitchOAuthError::InvalidRedirectUri`] when the redirect_uri isn't an absolute http or https url.
+///
+/// ```rust
+/// let url = twitch_oauth_async_std::build_authorize_url(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &["user:read:email".to_string(), "chat:read".to_string()],
+///     "state",
+/// )
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["response_type"], "code");
+/// assert_eq!(params["redirect_uri"], "http://localhost:3000/callback");
+/// assert_eq!(params["scope"], "user:read:email chat:read");
+/// assert_eq!(params["state"], "state");
+///
+/// // Joined, empty and duplicate scopes are normalized
+/// let url = twitch_oauth_async_std::build_authorize_url(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &[" chat:read  chat:edit".to_string(), "".to_string(), "chat:read".to_string()],
+///     "state",
+/// )
+/// .unwrap();
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["scope"], "chat:read chat:edit");
+///
+/// // A relative redirect_uri is caught before the browser round-trip
+/// assert!(twitch_oauth_async_std::build_authorize_url("client_id", "/callback", &[], "state").is_err());
+/// ```
+pub fn build_authorize_url(
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &[String],
+    state: &str,
+) -> Result<Url, TwitchOAuthError> {
+    AuthorizeUrl::new(client_id, redirect_uri, scopes, state).build()
+}
+
+/// A builder for the authorization code flow url, when [`build_authorize_url`] isn't enough
+///
+/// ```rust
+/// use twitch_oauth_async_std::AuthorizeUrl;
+///
+/// let url = AuthorizeUrl::new(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &["user:read:email".to_string()],
+///     "state",
+/// )
+/// .force_verify(true)
+/// .build()
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["force_verify"], "true");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthorizeUrl {
+    client_id: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    state: String,
+    response_type: &'static str,
+    code_challenge: Option<String>,
+    force_verify: bool,
+}
+
+impl AuthorizeUrl {
+    /// To start the authorization code flow, you need to provide your client_id, redirect_uri, a scope array and a state
+    pub fn new(client_id: &str, redirect_uri: &str, scopes: &[String], state: &str) -> Self {
+        AuthorizeUrl {
+            client_id: client_id.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes: scopes.to_vec(),
+            state: state.to_string(),
+            response_type: "code",
+            code_challenge: None,
+            force_verify: false,
+        }
+    }
+
+    /// Send the S256 code challenge of a [`Pkce`]
+    pub fn pkce(mut self, pkce: &Pkce) -> Self {
+        self.code_challenge = Some(pkce.code_challenge.clone());
+        self
+    }
+
+    /// Force Twitch to prompt the user again even if they already authorized your app, to switch accounts
+    pub fn force_verify(mut self, force_verify: bool) -> Self {
+        self.force_verify = force_verify;
+        self
+    }
+
+    /// Build the authorization url, failing when the redirect_uri isn't an absolute http or https url
+    pub fn build(&self) -> Result<Url, TwitchOAuthError> {
+        self.build_at(authorize_endpoint())
+    }
+
+    // `url` is the authorize endpoint without a query, see `TwitchOAuthClient::authorize_url`
+    pub(crate) fn build_at(&self, mut url: Url) -> Result<Url, TwitchOAuthError> {
+        check_redirect_uri(&self.redirect_uri)?;
+        let joinee_scopes = join_scopes(&self.scopes);
+
+        let mut params = HashMap::new();
+        params.insert("response_type", self.response_type);
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("redirect_uri", self.redirect_uri.as_str());
+        params.insert("scope", joinee_scopes.as_str());
+        params.insert("state", self.state.as_str());
+        if self.force_verify {
+            params.insert("force_verify", "true");
+        }
+
+        url.query_pairs_mut().extend_pairs(&params);
+        if let Some(code_challenge) = &self.code_challenge {
+            url.query_pairs_mut()
+                .append_pair("code_challenge", code_challenge)
+                .append_pair("code_challenge_method", "S256");
+        }
+
+        Ok(url)
+    }
+}
+
+impl TryFrom<AuthorizeUrl> for Url {
+    type Error = TwitchOAuthError;
+
+    fn try_from(url: AuthorizeUrl) -> Result<Self, TwitchOAuthError> {
+        url.build()
+    }
+}
+
+/// To start the authorization code flow with PKCE, you need to provide the same arguments as [`build_authorize_url`] and a [`Pkce`]
+///
+/// ```rust
+/// let pkce = twitch_oauth_async_std::Pkce::generate();
+/// let url = twitch_oauth_async_std::build_authorize_url_pkce(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &["user:read:email".to_string()],
+///     "state",
+///     &pkce,
+/// )
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["code_challenge"], pkce.code_challenge);
+/// assert_eq!(params["code_challenge_method"], "S256");
+/// ```
+pub fn build_authorize_url_pkce(
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &[String],
+    state: &str,
+    pkce: &Pkce,
+) -> Result<Url, TwitchOAuthError> {
+    AuthorizeUrl::new(client_id, redirect_uri, scopes, state)
+        .pkce(pkce)
+        .build()
+}
+
+/// The `response_type` of an authorization code or implicit grant flow, see [`AuthorizeOptions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorizeResponseType {
+    /// An authorization code to exchange, the default
+    Code,
+    /// An access token returned directly in the redirect fragment
+    Token,
+}
+
+// Not derived, as `#[default]` on a variant needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for AuthorizeResponseType {
+    fn default() -> Self {
+        AuthorizeResponseType::Code
+    }
+}
+
+impl AuthorizeResponseType {
+    /// The response type as Twitch spells it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthorizeResponseType::Code => "code",
+            AuthorizeResponseType::Token => "token",
+        }
+    }
+}
+
+/// Everything an authorization url can carry, for [`build_authorize_url_with`]
+///
+/// New parameters are added here rather than as new functions, so fill it with
+/// `..Default::default()` to keep compiling when they are.
+#[derive(Debug, Clone, Default)]
+pub struct AuthorizeOptions {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scopes: ScopeSet,
+    pub state: String,
+    /// Force Twitch to prompt the user again even if they already authorized your app
+    pub force_verify: bool,
+    pub response_type: AuthorizeResponseType,
+    /// Send the S256 code challenge of this [`Pkce`]
+    pub pkce: Option<Pkce>,
+}
+
+/// To build any authorization url, you need to provide its [`AuthorizeOptions`]
+///
+/// Fails with [`TwitchOAuthError::InvalidRedirectUri`] when the redirect_uri isn't an absolute http or https url.
+///
+/// ```rust
+/// use twitch_oauth_async_std::{AuthorizeOptions, AuthorizeResponseType, Scope, ScopeSet};
+///
+/// let options = AuthorizeOptions {
+///     client_id: "client_id".to_string(),
+///     redirect_uri: "http://localhost:3000/callback".to_string(),
+///     scopes: [Scope::ChatRead, Scope::ChatEdit].iter().copied().collect::<ScopeSet>(),
+///     state: "state".to_string(),
+///     force_verify: true,
+///     response_type: AuthorizeResponseType::Token,
+///     ..Default::default()
+/// };
+/// let url = twitch_oauth_async_std::build_authorize_url_with(&options).unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["response_type"], "token");
+/// assert_eq!(params["scope"], "chat:read chat:edit");
+/// assert_eq!(params["force_verify"], "true");
+/// assert!(!params.contains_key("code_challenge"));
+/// ```
+pub fn build_authorize_url_with(options: &AuthorizeOptions) -> Result<Url, TwitchOAuthError> {
+    authorize_url_for(options).build()
+}
+
+pub(crate) fn authorize_url_for(options: &AuthorizeOptions) -> AuthorizeUrl {
+    let scopes: Vec<String> = options
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str().to_string())
+        .collect();
+
+    let mut url = AuthorizeUrl::new(
+        &options.client_id,
+        &options.redirect_uri,
+        &scopes,
+        &options.state,
+    )
+    .force_verify(options.force_verify);
+    url.response_type = options.response_type.as_str();
+    if let Some(pkce) = &options.pkce {
+        url = url.pkce(pkce);
+    }
+
+    url
+}
+
+/// To ask a user for more scopes, you need to provide their validated token, the scopes to add, a redirect_uri and a state
+///
+/// The url requests the scopes the token already has as well as `new_scopes`, for the client_id
+/// the token was issued to, so the new token keeps working for every feature.
+///
+/// ```rust
+/// use twitch_oauth_async_std::{Scope, ValidatedToken};
+///
+/// let token: ValidatedToken = serde_json::from_str(
+///     r#"{"client_id":"client_id","login":"login","user_id":"1","scopes":["chat:read"]}"#,
+/// ).unwrap();
+/// let missing = token.missing_scopes_for(&[Scope::ChatRead, Scope::ChatEdit]);
+/// let url = twitch_oauth_async_std::build_incremental_authorize_url(
+///     &token,
+///     &missing,
+///     "http://localhost:3000/callback",
+///     "state",
+/// )
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["client_id"], "client_id");
+/// assert_eq!(params["scope"], "chat:read chat:edit");
+/// ```
+pub fn build_incremental_authorize_url(
+    existing: &ValidatedToken,
+    new_scopes: &[Scope],
+    redirect_uri: &str,
+    state: &str,
+) -> Result<Url, TwitchOAuthError> {
+    let mut scopes = existing.scopes.clone();
+    scopes.extend(new_scopes.iter().map(|scope| scope.as_str().to_string()));
+
+    AuthorizeUrl::new(&existing.client_id, redirect_uri, &scopes, state).build()
+}
+
+/// To start the implicit grant flow, you need to provide your client_id, redirect_uri, a scope array and a state
+///
+/// The access token comes back in the fragment of the redirect, see [`parse_implicit_fragment`].
+///
+/// ```rust
+/// let url = twitch_oauth_async_std::build_authorize_url_token(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &["user:read:email".to_string()],
+///     "state",
+/// )
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["response_type"], "token");
+/// ```
+pub fn build_authorize_url_token(
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &[String],
+    state: &str,
+) -> Result<Url, TwitchOAuthError> {
+    let mut url = AuthorizeUrl::new(client_id, redirect_uri, scopes, state);
+    url.response_type = "token";
+    url.build()
+}
+
+/// To parse the fragment of an implicit grant redirect, you need to provide the fragment, with or without its leading `#`
+///
+/// ```rust
+/// use twitch_oauth_async_std::CallbackError;
+///
+/// let token = twitch_oauth_async_std::parse_implicit_fragment(
+///     "#access_token=abc&scope=chat%3Aread+chat%3Aedit&state=xyz&token_type=bearer",
+/// )
+/// .unwrap();
+/// assert_eq!(token.access_token, "abc");
+/// assert_eq!(token.scope, vec!["chat:read", "chat:edit"]);
+/// assert_eq!(token.state, "xyz");
+///
+/// match twitch_oauth_async_std::parse_implicit_fragment("error=access_denied&state=xyz") {
+///     Err(CallbackError::Denied { error, .. }) => assert_eq!(error, "access_denied"),
+///     _ => panic!("expected a denied redirect"),
+/// }
+/// ```
+pub fn parse_implicit_fragment(fragment: &str) -> Result<ImplicitToken, CallbackError> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let params: HashMap<String, String> = url::form_urlencoded::parse(fragment.as_bytes())
+        .into_owned()
+        .collect();
+
+    if let Some(error) = params.get("error") {
+        return Err(CallbackError::Denied {
+            error: error.to_string(),
+            error_description: params.get("error_description").cloned(),
+        });
+    }
+
+    let access_token = params
+        .get("access_token")
+        .ok_or(CallbackError::MissingParameter("access_token"))?;
+    let state = params
+        .get("state")
+        .ok_or(CallbackError::MissingParameter("state"))?;
+    let scope = params
+        .get("scope")
+        .map(|scope| split_scopes(scope))
+        .unwrap_or_default();
+    let token_type = params
+        .get("token_type")
+        .cloned()
+        .unwrap_or_else(|| "bearer".to_string());
+
+    Ok(ImplicitToken {
+        access_token: access_token.to_string(),
+        scope,
+        state: state.to_string(),
+        token_type,
+    })
+}
+
+/// To parse the callback Twitch redirected to, you need to provide the callback URL
+///
+/// ```rust
+/// use twitch_oauth_async_std::CallbackError;
+/// use url::Url;
+///
+/// let url = Url::parse("https://myapp/cb?code=abc&scope=chat%3Aread+chat%3Aedit&state=xyz").unwrap();
+/// let callback = twitch_oauth_async_std::parse_callback(&url).unwrap();
+/// assert_eq!(callback.code, "abc");
+/// assert_eq!(callback.scope, vec!["chat:read", "chat:edit"]);
+/// assert_eq!(callback.state, "xyz");
+///
+/// let url = Url::parse("https://myapp/cb?error=access_denied&error_description=The+user+denied+you+access&state=xyz").unwrap();
+/// match twitch_oauth_async_std::parse_callback(&url) {
+///     Err(CallbackError::Denied { error, .. }) => assert_eq!(error, "access_denied"),
+///     _ => panic!("expected a denied callback"),
+/// }
+/// ```
+pub fn parse_callback(url: &Url) -> Result<AuthCallback, CallbackError> {
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    if let Some(error) = params.get("error") {
+        return Err(CallbackError::Denied {
+            error: error.to_string(),
+            error_description: params.get("error_description").cloned(),
+        });
+    }
+
+    let code = params
+        .get("code")
+        .ok_or(CallbackError::MissingParameter("code"))?;
+    let state = params
+        .get("state")
+        .ok_or(CallbackError::MissingParameter("state"))?;
+    let scope = params
+        .get("scope")
+        .map(|scope| split_scopes(scope))
+        .unwrap_or_default();
+
+    Ok(AuthCallback {
+        code: code.to_string(),
+        scope,
+        state: state.to_string(),
+    })
+}
@@ -0,0 +1,170 @@
+//! The HTTP backends the `surf::Client` of a `TwitchOAuthClient` sends requests with
+//!
+//! isahc on async-std is used by default, the `reqwest` feature swaps it for reqwest on tokio.
+//! Timeouts, retry backoff and device flow polling wait on the timer of the same runtime.
+
+use crate::error::TwitchOAuthError;
+use futures_util::future::{self, BoxFuture, Either};
+use std::future::Future;
+use std::time::Duration;
+use url::Url;
+
+/// What a [`TwitchOAuthClient`](crate::TwitchOAuthClient) configures on its backend
+#[derive(Debug, Default)]
+pub(crate) struct BackendConfig<'a> {
+    pub(crate) proxy: Option<&'a Url>,
+    pub(crate) pool_max_idle: Option<usize>,
+    #[cfg(feature = "dangerous-tls")]
+    pub(crate) accept_invalid_certs: bool,
+}
+
+/// Builds the `surf::Client` requests are sent with
+pub(crate) trait HttpBackend {
+    /// The client of `TwitchOAuthClient::new`
+    fn default_client() -> surf::Client;
+
+    /// A client with the proxy, pool size and TLS settings of `config`
+    fn build(config: &BackendConfig) -> Result<surf::Client, TwitchOAuthError>;
+
+    /// Waits for `duration` on the timer of the backend's runtime
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+#[cfg(all(feature = "isahc", not(feature = "reqwest")))]
+pub(crate) type DefaultBackend = Isahc;
+#[cfg(feature = "reqwest")]
+pub(crate) type DefaultBackend = Reqwest;
+
+/// Runs `future` for at most `duration`, `None` when it took longer
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    futures_util::pin_mut!(future);
+    match future::select(future, DefaultBackend::sleep(duration)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(all(feature = "isahc", not(feature = "reqwest")))]
+pub(crate) struct Isahc;
+
+#[cfg(all(feature = "isahc", not(feature = "reqwest")))]
+impl HttpBackend for Isahc {
+    fn default_client() -> surf::Client {
+        surf::Client::with_http_client(http_client::isahc::IsahcClient::new())
+    }
+
+    fn build(config: &BackendConfig) -> Result<surf::Client, TwitchOAuthError> {
+        use isahc::config::Configurable;
+
+        let mut builder = isahc::HttpClient::builder();
+        if let Some(proxy) = config.proxy {
+            let uri: isahc::http::Uri =
+                proxy
+                    .as_str()
+                    .parse()
+                    .map_err(|e: isahc::http::uri::InvalidUri| {
+                        TwitchOAuthError::ClientBuild(e.to_string())
+                    })?;
+            builder = builder.proxy(uri);
+        }
+        if let Some(max) = config.pool_max_idle {
+            builder = builder.connection_cache_size(max);
+        }
+        #[cfg(feature = "dangerous-tls")]
+        if config.accept_invalid_certs {
+            builder = builder.ssl_options(isahc::config::SslOption::DANGER_ACCEPT_INVALID_CERTS);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| TwitchOAuthError::ClientBuild(e.to_string()))?;
+
+        Ok(surf::Client::with_http_client(
+            http_client::isahc::IsahcClient::from_client(client),
+        ))
+    }
+
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+#[cfg(feature = "reqwest")]
+pub(crate) struct Reqwest;
+
+#[cfg(feature = "reqwest")]
+impl HttpBackend for Reqwest {
+    fn default_client() -> surf::Client {
+        surf::Client::with_http_client(ReqwestClient(reqwest::Client::new()))
+    }
+
+    fn build(config: &BackendConfig) -> Result<surf::Client, TwitchOAuthError> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = config.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|e| TwitchOAuthError::ClientBuild(e.to_string()))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(max) = config.pool_max_idle {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        #[cfg(feature = "dangerous-tls")]
+        if config.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| TwitchOAuthError::ClientBuild(e.to_string()))?;
+
+        Ok(surf::Client::with_http_client(ReqwestClient(client)))
+    }
+
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Sends the requests of surf with reqwest
+#[cfg(feature = "reqwest")]
+#[derive(Debug)]
+struct ReqwestClient(reqwest::Client);
+
+#[cfg(feature = "reqwest")]
+#[async_trait::async_trait]
+impl http_client::HttpClient for ReqwestClient {
+    async fn send(
+        &self,
+        mut req: http_client::Request,
+    ) -> Result<http_client::Response, http_client::Error> {
+        use http_client::http_types::StatusCode;
+
+        let method = reqwest::Method::from_bytes(req.method().to_string().as_bytes())
+            .map_err(|e| http_client::Error::from_str(StatusCode::BadRequest, e))?;
+        let mut builder = self.0.request(method, req.url().as_str());
+        for (name, values) in req.iter() {
+            for value in values {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+        let body = req.take_body().into_bytes().await?;
+
+        let res = builder
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| http_client::Error::from_str(StatusCode::BadGateway, e))?;
+        let mut response = http_client::Response::new(res.status().as_u16());
+        for (name, value) in res.headers() {
+            let value = value
+                .to_str()
+                .map_err(|e| http_client::Error::from_str(StatusCode::BadGateway, e))?;
+            response.append_header(name.as_str(), value);
+        }
+        let body = res
+            .bytes()
+            .await
+            .map_err(|e| http_client::Error::from_str(StatusCode::BadGateway, e))?;
+        response.set_body(body.to_vec());
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,163 @@
+//! Blocking versions of the crate functions, enabled with the `blocking` feature
+//!
+//! Every function runs its async counterpart on `async_std::task::block_on`, so the feature needs
+//! the default isahc backend rather than `reqwest`.
+//! They must not be called from within an async context, as they block the executor thread.
+
+use crate::{
+    AppAccessToken, DeviceCodeResponse, DeviceFlowError, OpenIdConfiguration, RevokeOutcome, Scope,
+    TwitchOAuthError, UserAccessToken, UserInfo, ValidatedToken,
+};
+use async_std::task::block_on;
+use std::collections::HashMap;
+
+/// See [`crate::get_app_access_token`]
+///
+/// ```rust,no_run
+/// let token = twitch_oauth_async_std::blocking::get_app_access_token("client_id", "client_secret");
+/// ```
+pub fn get_app_access_token(
+    client_id: &str,
+    client_secret: &str,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    block_on(crate::get_app_access_token(client_id, client_secret))
+}
+
+/// See [`crate::get_app_access_token_with_scopes`]
+pub fn get_app_access_token_with_scopes(
+    client_id: &str,
+    client_secret: &str,
+    scopes: Vec<String>,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    block_on(crate::get_app_access_token_with_scopes(
+        client_id,
+        client_secret,
+        scopes,
+    ))
+}
+
+/// See [`crate::get_app_access_token_with_params`]
+pub fn get_app_access_token_with_params(
+    client_id: &str,
+    client_secret: &str,
+    extra: &HashMap<&str, &str>,
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    block_on(crate::get_app_access_token_with_params(
+        client_id,
+        client_secret,
+        extra,
+    ))
+}
+
+/// See [`crate::get_app_access_token_with_typed_scopes`]
+pub fn get_app_access_token_with_typed_scopes(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[Scope],
+) -> Result<AppAccessToken, TwitchOAuthError> {
+    block_on(crate::get_app_access_token_with_typed_scopes(
+        client_id,
+        client_secret,
+        scopes,
+    ))
+}
+
+/// See [`crate::validate_token`]
+pub fn validate_token(access_token: &str) -> Result<ValidatedToken, TwitchOAuthError> {
+    block_on(crate::validate_token(access_token))
+}
+
+/// See [`crate::get_userinfo`]
+pub fn get_userinfo(access_token: &str) -> Result<UserInfo, TwitchOAuthError> {
+    block_on(crate::get_userinfo(access_token))
+}
+
+/// See [`crate::get_openid_configuration`]
+pub fn get_openid_configuration() -> Result<OpenIdConfiguration, TwitchOAuthError> {
+    block_on(crate::get_openid_configuration())
+}
+
+/// See [`crate::revoke_token`]
+pub fn revoke_token(
+    access_token: &str,
+    client_id: &str,
+) -> Result<RevokeOutcome, TwitchOAuthError> {
+    block_on(crate::revoke_token(access_token, client_id))
+}
+
+/// See [`crate::revoke_tokens`]
+pub fn revoke_tokens(
+    access_tokens: &[&str],
+    client_id: &str,
+) -> Vec<Result<RevokeOutcome, TwitchOAuthError>> {
+    block_on(crate::revoke_tokens(access_tokens, client_id))
+}
+
+/// See [`crate::remoke_token`]
+#[deprecated(note = "use revoke_token")]
+#[allow(deprecated)]
+pub fn remoke_token(
+    access_token: &str,
+    client_id: &str,
+) -> Result<surf::StatusCode, TwitchOAuthError> {
+    block_on(crate::remoke_token(access_token, client_id))
+}
+
+/// See [`crate::exchange_code`]
+pub fn exchange_code(
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    block_on(crate::exchange_code(
+        client_id,
+        client_secret,
+        code,
+        redirect_uri,
+    ))
+}
+
+/// See [`crate::exchange_code_pkce`]
+pub fn exchange_code_pkce(
+    client_id: &str,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    block_on(crate::exchange_code_pkce(
+        client_id,
+        code,
+        redirect_uri,
+        code_verifier,
+    ))
+}
+
+/// See [`crate::refresh_token`]
+pub fn refresh_token(
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    block_on(crate::refresh_token(
+        client_id,
+        client_secret,
+        refresh_token,
+    ))
+}
+
+/// See [`crate::start_device_flow`]
+pub fn start_device_flow(
+    client_id: &str,
+    scopes: &[String],
+) -> Result<DeviceCodeResponse, TwitchOAuthError> {
+    block_on(crate::start_device_flow(client_id, scopes))
+}
+
+/// See [`crate::poll_device_token`]
+pub fn poll_device_token(
+    client_id: &str,
+    device_code: &str,
+) -> Result<UserAccessToken, DeviceFlowError> {
+    block_on(crate::poll_device_token(client_id, device_code))
+}
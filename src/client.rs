@@ -0,0 +1,994 @@
+//! The client sending every request
+
+use crate::authorize::{authorize_url_for, AuthorizeOptions};
+use crate::backend::{timeout, BackendConfig, DefaultBackend, HttpBackend};
+use crate::clock::{Clock, SystemClock};
+use crate::endpoints::Endpoints;
+use crate::error::TwitchOAuthError;
+use crate::oidc::JwksCache;
+use crate::token::{Secret, DEFAULT_EXPIRY_SKEW};
+use crate::user_token::CodeGuard;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use surf::RequestBuilder;
+use url::Url;
+
+/// The rate limit Twitch reported in the `Ratelimit-*` headers of a response
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp in seconds when the bucket resets
+    pub reset: u64,
+}
+
+impl RateLimit {
+    fn from_response(res: &surf::Response) -> Option<RateLimit> {
+        fn header<T: std::str::FromStr>(res: &surf::Response, name: &str) -> Option<T> {
+            res.header(name)?.as_str().trim().parse().ok()
+        }
+
+        Some(RateLimit {
+            limit: header(res, "Ratelimit-Limit")?,
+            remaining: header(res, "Ratelimit-Remaining")?,
+            reset: header(res, "Ratelimit-Reset")?,
+        })
+    }
+
+    /// When the bucket resets
+    pub fn reset_at(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(self.reset)
+    }
+}
+
+// How long until the `Ratelimit-Reset` of a response, even without the other rate limit headers
+fn rate_limit_reset_wait(res: &surf::Response) -> Option<Duration> {
+    let reset: u64 = res
+        .header("Ratelimit-Reset")?
+        .as_str()
+        .trim()
+        .parse()
+        .ok()?;
+    let reset_at = SystemTime::UNIX_EPOCH + Duration::from_secs(reset);
+    Some(
+        reset_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// What a request to Twitch looked like, passed to the [`TwitchOAuthClient::with_metrics`] hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// The endpoint path, e.g. `/oauth2/token`, without the query string carrying the secrets
+    pub endpoint: String,
+    /// The HTTP status, `None` when the request failed or timed out before a response
+    pub status: Option<u16>,
+    /// How long the request took, not counting a wait for the rate limit to reset
+    pub elapsed: Duration,
+}
+
+/// Counts of a batch of results, returned by [`TwitchOAuthClient::validate_tokens_summary`] and [`TwitchOAuthClient::revoke_tokens_summary`]
+///
+/// `failed` counts every error, `rate_limited` the ones among them Twitch answered with a 429.
+/// Displays as a single line, to log one summary per run.
+///
+/// ```rust
+/// use twitch_oauth_async_std::{BatchSummary, TwitchOAuthError};
+///
+/// let results: Vec<Result<(), TwitchOAuthError>> = vec![
+///     Ok(()),
+///     Err(TwitchOAuthError::Api { status: 429, message: "Too Many Requests".to_string() }),
+///     Err(TwitchOAuthError::Timeout),
+/// ];
+/// let summary: BatchSummary = results.iter().collect();
+/// assert_eq!(summary, BatchSummary { total: 3, succeeded: 1, failed: 2, rate_limited: 1 });
+/// assert_eq!(summary.to_string(), "3 total, 1 succeeded, 2 failed (1 rate limited)");
+///
+/// // With the tokens next to their results, as `validate_tokens` returns them
+/// let results: Vec<(String, Result<(), TwitchOAuthError>)> = vec![("token".to_string(), Ok(()))];
+/// let summary: BatchSummary = results.iter().map(|(_, result)| result).collect();
+/// assert_eq!(summary.succeeded, 1);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub rate_limited: usize,
+}
+
+impl<'a, T: 'a> std::iter::FromIterator<&'a Result<T, TwitchOAuthError>> for BatchSummary {
+    fn from_iter<I: IntoIterator<Item = &'a Result<T, TwitchOAuthError>>>(iter: I) -> Self {
+        let mut summary = BatchSummary::default();
+        for result in iter {
+            summary.total += 1;
+            match result {
+                Ok(_) => summary.succeeded += 1,
+                Err(e) => {
+                    summary.failed += 1;
+                    if e.status() == Some(429) {
+                        summary.rate_limited += 1;
+                    }
+                }
+            }
+        }
+        summary
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} total, {} succeeded, {} failed ({} rate limited)",
+            self.total, self.succeeded, self.failed, self.rate_limited
+        )
+    }
+}
+
+// Called after every request, see `TwitchOAuthClient::with_metrics`
+#[derive(Clone)]
+struct Metrics(Arc<dyn Fn(&RequestMetrics) + Send + Sync>);
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+// Called on every outgoing request, see `TwitchOAuthClient::with_before_send`
+#[derive(Clone)]
+struct BeforeSend(Arc<dyn Fn(surf::Request) -> surf::Request + Send + Sync>);
+
+impl fmt::Debug for BeforeSend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BeforeSend")
+    }
+}
+
+/// A client holding your credentials and a single `surf::Client` reused across requests
+///
+/// ```rust
+/// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+/// let token = client.app_access_token();
+/// ```
+///
+/// Cloning is cheap, clones share the connection pool, the last rate limit and the cached JWKS,
+/// so a client can be stored in the state of a web server and used from any task:
+///
+/// ```rust
+/// use twitch_oauth_async_std::TwitchOAuthClient;
+///
+/// fn assert_clone_send_sync<T: Clone + Send + Sync + 'static>() {}
+/// assert_clone_send_sync::<TwitchOAuthClient>();
+/// ```
+///
+/// `Debug` redacts the client_secret, so a client can be logged:
+///
+/// ```rust
+/// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+/// let debug = format!("{:?}", client);
+/// assert!(debug.contains(r#"client_secret: "cl...redacted""#));
+/// assert!(!debug.contains("client_secret\""));
+/// ```
+#[derive(Clone)]
+pub struct TwitchOAuthClient {
+    pub(crate) client_id: String,
+    pub(crate) client_secret: String,
+    pub(crate) client: surf::Client,
+    pub(crate) endpoints: Endpoints,
+    timeout: Duration,
+    deadline: Option<Duration>,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    rate_limit_wait: bool,
+    max_rate_limit_wait: Duration,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    headers: Vec<(String, String)>,
+    before_send: Option<BeforeSend>,
+    metrics: Option<Metrics>,
+    proxy: Option<Url>,
+    pool_max_idle: Option<usize>,
+    #[cfg(feature = "dangerous-tls")]
+    accept_invalid_certs: bool,
+    pub(crate) jwks: Arc<Mutex<Option<JwksCache>>>,
+    pub(crate) jwks_cache_ttl: Duration,
+    pub(crate) code_guard: Option<CodeGuard>,
+    user_agent: String,
+    accept_language: Option<String>,
+    pub(crate) concurrency: usize,
+    clock: Arc<dyn Clock>,
+    expiry_skew: Duration,
+}
+
+impl fmt::Debug for TwitchOAuthClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("TwitchOAuthClient");
+        debug
+            .field("client_id", &self.client_id)
+            .field(
+                "client_secret",
+                &Secret {
+                    value: &self.client_secret,
+                    full: false,
+                },
+            )
+            .field("client", &self.client)
+            .field("endpoints", &self.endpoints)
+            .field("timeout", &self.timeout)
+            .field("deadline", &self.deadline)
+            .field("max_retries", &self.max_retries)
+            .field("retry_base_delay", &self.retry_base_delay)
+            .field("rate_limit_wait", &self.rate_limit_wait)
+            .field("max_rate_limit_wait", &self.max_rate_limit_wait)
+            .field("rate_limit", &self.rate_limit)
+            .field("headers", &self.headers)
+            .field("before_send", &self.before_send)
+            .field("metrics", &self.metrics)
+            .field("proxy", &self.proxy)
+            .field("pool_max_idle", &self.pool_max_idle);
+        #[cfg(feature = "dangerous-tls")]
+        debug.field("accept_invalid_certs", &self.accept_invalid_certs);
+        debug
+            .field("jwks", &self.jwks)
+            .field("jwks_cache_ttl", &self.jwks_cache_ttl)
+            .field("code_guard", &self.code_guard)
+            .field("user_agent", &self.user_agent)
+            .field("accept_language", &self.accept_language)
+            .field("concurrency", &self.concurrency)
+            .field("clock", &self.clock)
+            .field("expiry_skew", &self.expiry_skew)
+            .finish()
+    }
+}
+
+impl TwitchOAuthClient {
+    /// To create a client, you need to provide your client_id and client_secret
+    pub fn new(client_id: &str, client_secret: &str) -> TwitchOAuthClient {
+        TwitchOAuthClient::with_client(DefaultBackend::default_client(), client_id, client_secret)
+    }
+
+    /// To create a client from the environment, you need to set `TWITCH_CLIENT_ID` and `TWITCH_CLIENT_SECRET`
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{TwitchOAuthClient, TwitchOAuthError};
+    ///
+    /// std::env::remove_var("TWITCH_CLIENT_SECRET");
+    /// std::env::set_var("TWITCH_CLIENT_ID", "client_id");
+    /// match TwitchOAuthClient::from_env() {
+    ///     Err(TwitchOAuthError::MissingEnvVar(names)) => assert_eq!(names, vec!["TWITCH_CLIENT_SECRET"]),
+    ///     other => panic!("expected a missing variable, got {:?}", other),
+    /// }
+    ///
+    /// std::env::set_var("TWITCH_CLIENT_SECRET", "client_secret");
+    /// let client = TwitchOAuthClient::from_env().unwrap();
+    /// assert_eq!(client.client_id(), "client_id");
+    /// ```
+    pub fn from_env() -> Result<TwitchOAuthClient, TwitchOAuthError> {
+        let client_id = std::env::var("TWITCH_CLIENT_ID");
+        let client_secret = std::env::var("TWITCH_CLIENT_SECRET");
+        match (client_id, client_secret) {
+            (Ok(client_id), Ok(client_secret)) => {
+                Ok(TwitchOAuthClient::new(&client_id, &client_secret))
+            }
+            (client_id, client_secret) => {
+                let mut missing = Vec::new();
+                if client_id.is_err() {
+                    missing.push("TWITCH_CLIENT_ID".to_string());
+                }
+                if client_secret.is_err() {
+                    missing.push("TWITCH_CLIENT_SECRET".to_string());
+                }
+                Err(TwitchOAuthError::MissingEnvVar(missing))
+            }
+        }
+    }
+
+    /// To create a client sending requests through any [`surf::HttpClient`], e.g. a fake one returning canned responses
+    ///
+    /// ```rust
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     #[cfg(feature = "reqwest")]
+    /// #     return tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future);
+    /// #     #[cfg(not(feature = "reqwest"))]
+    /// #     return async_std::task::block_on(future);
+    /// # }
+    /// use surf::http::{Request, Response};
+    /// use surf::{Error, HttpClient, StatusCode};
+    ///
+    /// #[derive(Debug)]
+    /// struct FakeClient;
+    ///
+    /// #[twitch_oauth_async_std::async_trait]
+    /// impl HttpClient for FakeClient {
+    ///     async fn send(&self, req: Request) -> Result<Response, Error> {
+    ///         assert_eq!(req.url().path(), "/oauth2/token");
+    ///         let mut res = Response::new(StatusCode::Ok);
+    ///         res.set_body(r#"{"access_token":"token","expires_in":3600,"token_type":"bearer"}"#);
+    ///         res.set_content_type(surf::http::mime::JSON);
+    ///         Ok(res)
+    ///     }
+    /// }
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::with_http_client(FakeClient, "client_id", "client_secret");
+    /// let token = block_on(client.app_access_token()).unwrap();
+    /// assert_eq!(token.access_token, "token");
+    /// ```
+    pub fn with_http_client<C: surf::HttpClient>(
+        http_client: C,
+        client_id: &str,
+        client_secret: &str,
+    ) -> TwitchOAuthClient {
+        TwitchOAuthClient::with_client(
+            surf::Client::with_http_client(http_client),
+            client_id,
+            client_secret,
+        )
+    }
+
+    /// To create a client sending requests through your own `surf::Client`, e.g. one configured with middleware
+    ///
+    /// ```rust
+    /// # fn build(surf_client: surf::Client) {
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::with_client(surf_client, "client_id", "client_secret");
+    /// # }
+    /// ```
+    pub fn with_client(
+        client: surf::Client,
+        client_id: &str,
+        client_secret: &str,
+    ) -> TwitchOAuthClient {
+        TwitchOAuthClient {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            client,
+            endpoints: Endpoints::twitch(),
+            timeout: Duration::from_secs(30),
+            deadline: None,
+            max_retries: 0,
+            retry_base_delay: Duration::from_millis(100),
+            rate_limit_wait: false,
+            max_rate_limit_wait: Duration::from_secs(60),
+            rate_limit: Arc::new(Mutex::new(None)),
+            headers: Vec::new(),
+            before_send: None,
+            metrics: None,
+            proxy: None,
+            pool_max_idle: None,
+            #[cfg(feature = "dangerous-tls")]
+            accept_invalid_certs: false,
+            jwks: Arc::new(Mutex::new(None)),
+            jwks_cache_ttl: Duration::from_secs(60 * 60),
+            code_guard: None,
+            concurrency: 10,
+            clock: Arc::new(SystemClock),
+            expiry_skew: DEFAULT_EXPIRY_SKEW,
+            user_agent: concat!("twitch-oauth-async-std/", env!("CARGO_PKG_VERSION")).to_string(),
+            accept_language: None,
+        }
+    }
+
+    /// Route every request through an HTTP or HTTPS proxy
+    ///
+    /// This replaces the underlying `surf::Client`, including one given to [`TwitchOAuthClient::with_client`].
+    ///
+    /// ```rust
+    /// let proxy = url::Url::parse("http://proxy.internal:3128").unwrap();
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_proxy(proxy)
+    ///     .unwrap();
+    /// ```
+    pub fn with_proxy(mut self, proxy: Url) -> Result<TwitchOAuthClient, TwitchOAuthError> {
+        self.proxy = Some(proxy);
+        self.client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    /// Keep at most `max` idle connections open for reuse, `0` closes every connection after its request
+    ///
+    /// By default the backend sizes the pool itself, growing it with the number of requests in
+    /// flight. When validating many tokens, a value at least as large as
+    /// [`TwitchOAuthClient::with_concurrency`] keeps every batch on warm connections. Like
+    /// [`TwitchOAuthClient::with_proxy`], this replaces the underlying `surf::Client`.
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_concurrency(50)
+    ///     .with_pool_max_idle(50)
+    ///     .unwrap();
+    /// ```
+    pub fn with_pool_max_idle(mut self, max: usize) -> Result<TwitchOAuthClient, TwitchOAuthError> {
+        self.pool_max_idle = Some(max);
+        self.client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    /// **Dangerous**: accept any TLS certificate, e.g. a self-signed one on a local mock server
+    ///
+    /// With `true`, anyone on the network path can impersonate Twitch and read your client_secret
+    /// and tokens. Never use it in production, it is only available with the `dangerous-tls`
+    /// feature so it can't be enabled by accident. Like [`TwitchOAuthClient::with_proxy`], this
+    /// replaces the underlying `surf::Client`.
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_base_url("https://localhost:8443")
+    ///     .with_danger_accept_invalid_certs(true)
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "dangerous-tls")]
+    pub fn with_danger_accept_invalid_certs(
+        mut self,
+        accept: bool,
+    ) -> Result<TwitchOAuthClient, TwitchOAuthError> {
+        self.accept_invalid_certs = accept;
+        self.client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    fn build_http_client(&self) -> Result<surf::Client, TwitchOAuthError> {
+        DefaultBackend::build(&BackendConfig {
+            proxy: self.proxy.as_ref(),
+            pool_max_idle: self.pool_max_idle,
+            #[cfg(feature = "dangerous-tls")]
+            accept_invalid_certs: self.accept_invalid_certs,
+        })
+    }
+
+    /// Send `user_agent` as the `User-Agent` header, defaults to `twitch-oauth-async-std/<version>`
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_user_agent("my-bot/1.0 (+https://example.com)");
+    /// ```
+    pub fn with_user_agent(mut self, user_agent: &str) -> TwitchOAuthClient {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Send `language` as the `Accept-Language` header, e.g. `de-DE` or `fr, en;q=0.5`
+    ///
+    /// Where Twitch localizes an error, the message of [`TwitchOAuthError::Api`] is in that language.
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_accept_language("de-DE");
+    /// ```
+    pub fn with_accept_language(mut self, language: &str) -> TwitchOAuthClient {
+        self.accept_language = Some(language.to_string());
+        self
+    }
+
+    /// Send an extra header with every request, e.g. a `X-Request-Id` to correlate requests
+    ///
+    /// Headers set by the request itself win, and `authorization` is always ignored.
+    ///
+    /// ```rust
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     #[cfg(feature = "reqwest")]
+    /// #     return tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future);
+    /// #     #[cfg(not(feature = "reqwest"))]
+    /// #     return async_std::task::block_on(future);
+    /// # }
+    /// use surf::http::{Request, Response};
+    /// use surf::{Error, HttpClient, StatusCode};
+    ///
+    /// #[derive(Debug)]
+    /// struct FakeClient;
+    ///
+    /// #[twitch_oauth_async_std::async_trait]
+    /// impl HttpClient for FakeClient {
+    ///     async fn send(&self, req: Request) -> Result<Response, Error> {
+    ///         assert_eq!(req.header("x-request-id").unwrap().as_str(), "abc");
+    ///         assert_eq!(req.header("authorization").unwrap().as_str(), "OAuth token");
+    ///         let mut res = Response::new(StatusCode::Ok);
+    ///         res.set_body(r#"{"client_id":"id","login":null,"user_id":null,"scopes":[]}"#);
+    ///         res.set_content_type(surf::http::mime::JSON);
+    ///         Ok(res)
+    ///     }
+    /// }
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::with_http_client(FakeClient, "client_id", "client_secret")
+    ///     .with_header("X-Request-Id", "abc")
+    ///     .with_header("Authorization", "OAuth other");
+    /// block_on(client.validate("token")).unwrap();
+    /// ```
+    pub fn with_header(mut self, name: &str, value: &str) -> TwitchOAuthClient {
+        if !name.eq_ignore_ascii_case("authorization") {
+            self.headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Call `hook` on every outgoing request, e.g. to sign it or add tracing baggage
+    ///
+    /// It runs last, after the crate and [`TwitchOAuthClient::with_header`] set their headers, so
+    /// they can be inspected. It receives a `surf::Request` rather than a `surf::RequestBuilder`,
+    /// as only the former can be inspected.
+    ///
+    /// ```rust
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     #[cfg(feature = "reqwest")]
+    /// #     return tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future);
+    /// #     #[cfg(not(feature = "reqwest"))]
+    /// #     return async_std::task::block_on(future);
+    /// # }
+    /// use surf::http::{Request, Response};
+    /// use surf::{Error, HttpClient, StatusCode};
+    ///
+    /// #[derive(Debug)]
+    /// struct FakeClient;
+    ///
+    /// #[twitch_oauth_async_std::async_trait]
+    /// impl HttpClient for FakeClient {
+    ///     async fn send(&self, req: Request) -> Result<Response, Error> {
+    ///         assert_eq!(req.header("x-signature").unwrap().as_str(), "signed OAuth token");
+    ///         let mut res = Response::new(StatusCode::Ok);
+    ///         res.set_body(r#"{"client_id":"id","login":null,"user_id":null,"scopes":[]}"#);
+    ///         res.set_content_type(surf::http::mime::JSON);
+    ///         Ok(res)
+    ///     }
+    /// }
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::with_http_client(FakeClient, "client_id", "client_secret")
+    ///     .with_before_send(|mut req| {
+    ///         let signature = format!("signed {}", req.header("authorization").unwrap().as_str());
+    ///         req.insert_header("x-signature", signature);
+    ///         req
+    ///     });
+    /// block_on(client.validate("token")).unwrap();
+    /// ```
+    pub fn with_before_send<F>(mut self, hook: F) -> TwitchOAuthClient
+    where
+        F: Fn(surf::Request) -> surf::Request + Send + Sync + 'static,
+    {
+        self.before_send = Some(BeforeSend(Arc::new(hook)));
+        self
+    }
+
+    /// Call `hook` after every request completes or fails, e.g. to chart latency and error rates
+    ///
+    /// ```rust
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     #[cfg(feature = "reqwest")]
+    /// #     return tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future);
+    /// #     #[cfg(not(feature = "reqwest"))]
+    /// #     return async_std::task::block_on(future);
+    /// # }
+    /// use std::sync::{Arc, Mutex};
+    /// use twitch_oauth_async_std::{RequestMetrics, TwitchOAuthClient};
+    ///
+    /// let recorded: Arc<Mutex<Vec<RequestMetrics>>> = Arc::default();
+    /// let sink = recorded.clone();
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_base_url("http://127.0.0.1:1")
+    ///     .with_metrics(move |metrics| sink.lock().unwrap().push(metrics.clone()));
+    ///
+    /// assert!(block_on(client.validate("token")).is_err());
+    /// let recorded = recorded.lock().unwrap();
+    /// assert_eq!(recorded[0].endpoint, "/oauth2/validate");
+    /// assert_eq!(recorded[0].status, None);
+    /// ```
+    pub fn with_metrics<F>(mut self, hook: F) -> TwitchOAuthClient
+    where
+        F: Fn(&RequestMetrics) + Send + Sync + 'static,
+    {
+        self.metrics = Some(Metrics(Arc::new(hook)));
+        self
+    }
+
+    /// Check token expiry against `clock` instead of the system clock, e.g. a [`MockClock`](crate::MockClock) in tests
+    ///
+    /// Tokens received by this client are stamped with its clock, and [`UserTokenManager`](crate::UserTokenManager)
+    /// and [`AppTokenCache`](crate::AppTokenCache) decide when to refresh with it.
+    ///
+    /// ```rust
+    /// use std::time::SystemTime;
+    /// use twitch_oauth_async_std::{MockClock, TwitchOAuthClient};
+    ///
+    /// let clock = MockClock::new(SystemTime::UNIX_EPOCH);
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret").with_clock(clock.clone());
+    /// assert_eq!(client.clock().now(), SystemTime::UNIX_EPOCH);
+    /// ```
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> TwitchOAuthClient {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The clock token expiry is checked against
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Remember exchanged authorization codes for `ttl`, so a double-submitted callback doesn't exchange a code twice
+    ///
+    /// Exchanging a code again within `ttl` with the same redirect_uri and PKCE code_verifier
+    /// returns the token of the first exchange without asking Twitch. It fails with
+    /// [`TwitchOAuthError::CodeAlreadyExchanged`] while the first exchange is still running, or when
+    /// the code was exchanged with another redirect_uri or code_verifier. An exchange that fails or is cancelled, e.g. dropped by a timeout, is
+    /// forgotten so the code can be exchanged again. Codes are only remembered in memory, shared
+    /// between clones of this client.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_code_exchange_guard(Duration::from_secs(10 * 60));
+    /// ```
+    pub fn with_code_exchange_guard(mut self, ttl: Duration) -> TwitchOAuthClient {
+        self.code_guard = Some(CodeGuard::new(ttl));
+        self
+    }
+
+    /// Cache the keys id_tokens are verified with for `ttl`, defaults to an hour
+    ///
+    /// Keys are fetched again before `ttl` runs out when a token is signed with an unknown one, at
+    /// most once a minute.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_jwks_cache_ttl(Duration::from_secs(24 * 60 * 60));
+    /// ```
+    pub fn with_jwks_cache_ttl(mut self, ttl: Duration) -> TwitchOAuthClient {
+        self.jwks_cache_ttl = ttl;
+        self
+    }
+
+    /// Treat tokens as expired `skew` early, so one isn't used just before Twitch rejects it, defaults to 30 seconds
+    ///
+    /// Used by [`UserTokenManager`](crate::UserTokenManager) and [`AppTokenCache`](crate::AppTokenCache)
+    /// to decide when to refresh. Raise it when the local clock may lag further behind Twitch's.
+    /// The `is_expired` and `expires_within` methods of tokens always use the default skew, pass
+    /// this one to their `expires_within_skewed` instead.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_expiry_skew(Duration::from_secs(120));
+    /// assert_eq!(client.expiry_skew(), Duration::from_secs(120));
+    /// ```
+    pub fn with_expiry_skew(mut self, skew: Duration) -> TwitchOAuthClient {
+        self.expiry_skew = skew;
+        self
+    }
+
+    /// How early tokens are treated as expired
+    pub fn expiry_skew(&self) -> Duration {
+        self.expiry_skew
+    }
+
+    /// Run at most `concurrency` requests at once in batch operations, defaults to 10
+    pub fn with_concurrency(mut self, concurrency: usize) -> TwitchOAuthClient {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Wait until the rate limit resets before sending a request when no requests are remaining
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_rate_limit_wait(true);
+    /// ```
+    pub fn with_rate_limit_wait(mut self, wait: bool) -> TwitchOAuthClient {
+        self.rate_limit_wait = wait;
+        self
+    }
+
+    /// Wait at most `max` for a rate limit to reset, defaults to a minute
+    ///
+    /// A retried 429 whose `Ratelimit-Reset` is further away isn't retried, the 429 is returned
+    /// instead, and [`TwitchOAuthClient::with_rate_limit_wait`] sends the request without waiting.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_retries(3, Duration::from_millis(200))
+    ///     .with_max_rate_limit_wait(Duration::from_secs(10));
+    /// ```
+    pub fn with_max_rate_limit_wait(mut self, max: Duration) -> TwitchOAuthClient {
+        self.max_rate_limit_wait = max;
+        self
+    }
+
+    /// The last rate limit Twitch reported, shared between clones of this client
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Retry app token requests and validations up to `max` times on 5xx, 429, transport errors and timeouts
+    ///
+    /// The delay doubles after every attempt starting from `base_delay`, with added jitter.
+    /// A 429 with a `Ratelimit-Reset` header waits until the bucket resets instead, up to
+    /// [`TwitchOAuthClient::with_max_rate_limit_wait`].
+    /// Revocations, code exchanges and refreshes are never retried.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_retries(3, Duration::from_millis(200));
+    /// ```
+    pub fn with_retries(mut self, max: u32, base_delay: Duration) -> TwitchOAuthClient {
+        self.max_retries = max;
+        self.retry_base_delay = base_delay;
+        self
+    }
+
+    /// Fail requests that take longer than `timeout` with [`TwitchOAuthError::Timeout`], defaults to 30 seconds
+    ///
+    /// With retries, this bounds each attempt, the same as [`TwitchOAuthClient::with_attempt_timeout`].
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_timeout(Duration::from_secs(5));
+    /// ```
+    pub fn with_timeout(mut self, timeout: Duration) -> TwitchOAuthClient {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Fail each attempt that takes longer than `timeout` with [`TwitchOAuthError::Timeout`], retrying it if retries are enabled
+    pub fn with_attempt_timeout(self, timeout: Duration) -> TwitchOAuthClient {
+        self.with_timeout(timeout)
+    }
+
+    /// Fail requests with [`TwitchOAuthError::DeadlineExceeded`] when they don't complete within `deadline`,
+    /// retries and the backoff between them included, there is no deadline by default
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_retries(3, Duration::from_millis(200))
+    ///     .with_attempt_timeout(Duration::from_secs(2))
+    ///     .with_deadline(Duration::from_secs(5));
+    /// ```
+    pub fn with_deadline(mut self, deadline: Duration) -> TwitchOAuthClient {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Send every request to `base_url` instead of `https://id.twitch.tv`, e.g. a local mock server
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_base_url("http://127.0.0.1:8080");
+    /// ```
+    ///
+    /// An invalid base url is reported by each request as [`TwitchOAuthError::UrlBuild`]:
+    ///
+    /// ```rust
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     #[cfg(feature = "reqwest")]
+    /// #     return tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future);
+    /// #     #[cfg(not(feature = "reqwest"))]
+    /// #     return async_std::task::block_on(future);
+    /// # }
+    /// use twitch_oauth_async_std::{TwitchOAuthClient, TwitchOAuthError};
+    ///
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret").with_base_url("not a url");
+    /// let err = block_on(client.app_access_token()).unwrap_err();
+    /// assert!(matches!(err, TwitchOAuthError::UrlBuild(_)));
+    /// ```
+    pub fn with_base_url(mut self, base_url: &str) -> TwitchOAuthClient {
+        self.endpoints = Endpoints::with_base_url(base_url);
+        self
+    }
+
+    /// Send each request to its url in `endpoints`, e.g. to point the token endpoint at one mock server and the others at another
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{Endpoints, TwitchOAuthClient};
+    ///
+    /// let endpoints = Endpoints {
+    ///     token: "http://127.0.0.1:8081/oauth2/token".to_string(),
+    ///     ..Endpoints::with_base_url("http://127.0.0.1:8080")
+    /// };
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret").with_endpoints(endpoints);
+    /// assert_eq!(client.endpoints().token, "http://127.0.0.1:8081/oauth2/token");
+    /// ```
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> TwitchOAuthClient {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// The urls requests are sent to
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Build the authorization url of `options` against the authorize endpoint of this client
+    ///
+    /// Fails with [`TwitchOAuthError::UrlBuild`] when that endpoint isn't a valid url, see
+    /// [`build_authorize_url_with`](crate::build_authorize_url_with) for the other errors.
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{AuthorizeOptions, TwitchOAuthClient};
+    ///
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_base_url("http://127.0.0.1:8080");
+    /// let url = client
+    ///     .authorize_url(&AuthorizeOptions {
+    ///         client_id: "client_id".to_string(),
+    ///         redirect_uri: "http://localhost:3000/callback".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(url.path(), "/oauth2/authorize");
+    /// assert_eq!(url.port(), Some(8080));
+    /// ```
+    pub fn authorize_url(&self, options: &AuthorizeOptions) -> Result<Url, TwitchOAuthError> {
+        let endpoint = Url::parse(&self.endpoints.authorize).map_err(TwitchOAuthError::UrlBuild)?;
+        authorize_url_for(options).build_at(endpoint)
+    }
+
+    pub(crate) async fn send(
+        &self,
+        req: RequestBuilder,
+    ) -> Result<surf::Response, TwitchOAuthError> {
+        self.within_deadline(self.send_attempt(req)).await
+    }
+
+    async fn within_deadline<F>(&self, request: F) -> Result<surf::Response, TwitchOAuthError>
+    where
+        F: Future<Output = Result<surf::Response, TwitchOAuthError>>,
+    {
+        // Boxed, as a whole request nested in another layer is enough to overflow a 2MB stack in debug builds
+        let request = Box::pin(request);
+        match self.deadline {
+            Some(deadline) => timeout(deadline, request)
+                .await
+                .unwrap_or(Err(TwitchOAuthError::DeadlineExceeded)),
+            None => request.await,
+        }
+    }
+
+    // With the `tracing` feature, every request gets a span with its endpoint path and status.
+    // The query string is never recorded as it carries the client_secret and tokens.
+    async fn send_attempt(&self, req: RequestBuilder) -> Result<surf::Response, TwitchOAuthError> {
+        let mut req = req.header("user-agent", self.user_agent.as_str());
+        if let Some(language) = &self.accept_language {
+            req = req.header("accept-language", language.as_str());
+        }
+        let mut req = req.build();
+        for (name, value) in &self.headers {
+            if req.header(name.as_str()).is_none() {
+                req.insert_header(name.as_str(), value.as_str());
+            }
+        }
+        if let Some(BeforeSend(hook)) = &self.before_send {
+            req = hook(req);
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            use tracing_futures::Instrument;
+
+            let span = tracing::debug_span!(
+                "twitch_oauth_request",
+                endpoint = req.url().path(),
+                status = tracing::field::Empty,
+            );
+            let result = self.send_request(req).instrument(span.clone()).await;
+            match &result {
+                Ok(res) => {
+                    span.record("status", &u64::from(u16::from(res.status())));
+                }
+                Err(e) => tracing::debug!(parent: &span, error = %e, "twitch oauth request failed"),
+            }
+            result
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        self.send_request(req).await
+    }
+
+    async fn send_request(&self, req: surf::Request) -> Result<surf::Response, TwitchOAuthError> {
+        if self.rate_limit_wait {
+            if let Some(rate_limit) = self.rate_limit().filter(|r| r.remaining == 0) {
+                let wait = rate_limit.reset_at().duration_since(SystemTime::now()).ok();
+                if let Some(wait) = wait.filter(|wait| *wait <= self.max_rate_limit_wait) {
+                    DefaultBackend::sleep(wait).await;
+                }
+            }
+        }
+
+        let endpoint = req.url().path().to_string();
+        let start = Instant::now();
+        let result = match timeout(self.timeout, self.client.send(req)).await {
+            Some(res) => res.map_err(TwitchOAuthError::from),
+            None => Err(TwitchOAuthError::Timeout),
+        };
+        if let Some(Metrics(hook)) = &self.metrics {
+            hook(&RequestMetrics {
+                endpoint,
+                status: result.as_ref().ok().map(|res| res.status().into()),
+                elapsed: start.elapsed(),
+            });
+        }
+
+        let res = result?;
+        if let Some(rate_limit) = RateLimit::from_response(&res) {
+            *self.rate_limit.lock().unwrap() = Some(rate_limit);
+        }
+
+        Ok(res)
+    }
+
+    // Only used for idempotent requests, `build` is called again for every attempt
+    pub(crate) async fn send_with_retries<F>(
+        &self,
+        build: F,
+    ) -> Result<surf::Response, TwitchOAuthError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        self.within_deadline(self.send_with_retries_inner(build))
+            .await
+    }
+
+    async fn send_with_retries_inner<F>(&self, build: F) -> Result<surf::Response, TwitchOAuthError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = self.send_attempt(build()).await;
+            let retryable = match &result {
+                Ok(res) => {
+                    res.status().is_server_error()
+                        || res.status() == surf::StatusCode::TooManyRequests
+                }
+                Err(TwitchOAuthError::Http(_)) | Err(TwitchOAuthError::Timeout) => true,
+                Err(_) => false,
+            };
+            if !retryable || attempt >= self.max_retries {
+                return result;
+            }
+            // Twitch says exactly when a 429 can be retried, backing off would be a guess
+            let delay = match &result {
+                Ok(res) if res.status() == surf::StatusCode::TooManyRequests => {
+                    match rate_limit_reset_wait(res) {
+                        Some(wait) if wait > self.max_rate_limit_wait => return result,
+                        Some(wait) => wait,
+                        None => self.backoff(attempt),
+                    }
+                }
+                _ => self.backoff(attempt),
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "retrying twitch oauth request"
+            );
+            DefaultBackend::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_base_delay
+            .checked_mul(1 << attempt.min(16))
+            .unwrap_or(self.retry_base_delay);
+        let jitter = rand::thread_rng().gen_range(0, delay.as_millis() as u64 / 2 + 1);
+        delay + Duration::from_millis(jitter)
+    }
+
+    /// The client_id this client was created with
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+}
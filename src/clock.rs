@@ -0,0 +1,78 @@
+//! The clock token expiry is checked against
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Tells the current time, so expiry can be tested without sleeping
+///
+/// A [`TwitchOAuthClient`](crate::TwitchOAuthClient) uses [`SystemClock`] unless given another
+/// one with `with_clock`.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time
+    fn now(&self) -> SystemTime;
+}
+
+/// The system clock, used by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock only moving when told to, for tests
+///
+/// Clones share the same time, so a test can keep one and give another to the client.
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use twitch_oauth_async_std::{MockClock, UserAccessToken};
+///
+/// let clock = MockClock::new(SystemTime::now());
+/// let mut token: UserAccessToken = serde_json::from_str(
+///     r#"{"access_token":"a","refresh_token":"r","expires_in":3600,"scope":[],"token_type":"bearer"}"#,
+/// ).unwrap();
+/// token.obtained_at = clock.now();
+/// assert!(!token.is_expired_with(&clock));
+///
+/// // Tokens are treated as expired 30 seconds early to allow for clock skew
+/// clock.advance(Duration::from_secs(3570));
+/// assert!(token.is_expired_with(&clock));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl MockClock {
+    /// To create a mock clock, you need to provide the time it starts at
+    pub fn new(now: SystemTime) -> MockClock {
+        MockClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// The current time of this clock
+    pub fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Set the clock to `now`
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        MockClock::now(self)
+    }
+}
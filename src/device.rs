@@ -0,0 +1,274 @@
+//! The device code flow
+
+#[cfg(feature = "client")]
+use crate::backend::{DefaultBackend, HttpBackend};
+#[cfg(feature = "client")]
+use crate::client::TwitchOAuthClient;
+use crate::error::TwitchOAuthError;
+#[cfg(feature = "client")]
+use crate::error::{read_body, read_json, TwitchApiError};
+#[cfg(feature = "client")]
+use crate::scope::join_scopes;
+use crate::user_token::UserAccessToken;
+#[cfg(feature = "client")]
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "client")]
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "client")]
+use url::Url;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: usize,
+    pub interval: usize,
+}
+
+// To use the `{}` marker, the trait `fmt::Display` must be implemented
+// manually for the type.
+impl fmt::Display for DeviceCodeResponse {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string1 = format!("device_code: {}\n", self.device_code);
+        let string2 = format!("{} user_code: {}\n", string1, self.user_code);
+        let string3 = format!("{} verification_uri: {}\n", string2, self.verification_uri);
+        let string4 = format!("{} expires_in: {}\n", string3, self.expires_in);
+        let string5 = format!("{} interval: {}\n", string4, self.interval);
+        write!(f, "{}", string5)
+    }
+}
+
+/// Errors returned while polling for a device flow token
+#[derive(Debug)]
+pub enum DeviceFlowError {
+    /// The user has not entered the code yet, poll again after `interval`
+    AuthorizationPending,
+    /// Polling too fast, wait for `interval` seconds if provided or add 5 seconds to the current interval
+    SlowDown { interval: Option<usize> },
+    /// The device code expired, start a new device flow
+    ExpiredToken,
+    /// The user denied the authorization request
+    AccessDenied,
+    /// Any other request or API failure
+    Other(TwitchOAuthError),
+}
+
+impl fmt::Display for DeviceFlowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceFlowError::AuthorizationPending => write!(f, "authorization pending"),
+            DeviceFlowError::SlowDown { interval } => write!(f, "slow down: {:?}", interval),
+            DeviceFlowError::ExpiredToken => write!(f, "expired token"),
+            DeviceFlowError::AccessDenied => write!(f, "access denied"),
+            DeviceFlowError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DeviceFlowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeviceFlowError::Other(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TwitchOAuthError> for DeviceFlowError {
+    fn from(e: TwitchOAuthError) -> Self {
+        DeviceFlowError::Other(e)
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<surf::Error> for DeviceFlowError {
+    fn from(e: surf::Error) -> Self {
+        DeviceFlowError::Other(e.into())
+    }
+}
+
+/// The events of [`device_flow_stream`], the stream ends after `Authorized`, `Denied`, `Expired` or `Error`
+#[derive(Debug)]
+pub enum DeviceFlowEvent {
+    /// The user has not entered the code yet
+    Pending,
+    /// Twitch asked to poll slower, the stream now waits `interval` between polls
+    SlowDown { interval: Duration },
+    /// The user authorized the device
+    Authorized(UserAccessToken),
+    /// The user denied the authorization
+    Denied,
+    /// The device code expired before the user entered it
+    Expired,
+    /// The poll failed for another reason
+    Error(TwitchOAuthError),
+}
+
+#[cfg(feature = "client")]
+impl TwitchOAuthClient {
+    /// Start the device code flow with a scope array
+    pub async fn start_device_flow(
+        &self,
+        scopes: &[String],
+    ) -> Result<DeviceCodeResponse, TwitchOAuthError> {
+        let joinee_scopes = join_scopes(scopes);
+
+        let mut params = HashMap::new();
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("scopes", joinee_scopes.as_str());
+        let url = Url::parse_with_params(&self.endpoints.device, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let req = self.client.post(&url);
+        let mut res = self.send(req).await?;
+        let resp: DeviceCodeResponse = read_json(&mut res).await?;
+
+        Ok(resp)
+    }
+
+    /// Poll for a device flow token
+    pub async fn poll_device_token(
+        &self,
+        device_code: &str,
+    ) -> Result<UserAccessToken, DeviceFlowError> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "urn:ietf:params:oauth:grant-type:device_code");
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("device_code", device_code);
+        let url = Url::parse_with_params(&self.endpoints.token, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let req = self.client.post(&url);
+        let mut res = self.send(req).await?;
+        if !res.status().is_success() {
+            let body = read_body(&mut res).await?;
+            let err = TwitchApiError::parse(res.status(), &body);
+            return Err(match err.message.as_str() {
+                "authorization_pending" => DeviceFlowError::AuthorizationPending,
+                "slow_down" => DeviceFlowError::SlowDown {
+                    interval: err.interval,
+                },
+                "expired_token" => DeviceFlowError::ExpiredToken,
+                "access_denied" => DeviceFlowError::AccessDenied,
+                _ => DeviceFlowError::Other(err.into()),
+            });
+        }
+        let mut resp: UserAccessToken = read_json(&mut res).await?;
+        resp.obtained_at = self.clock().now();
+
+        Ok(resp)
+    }
+
+    /// Poll for a device flow token every `interval` until the user authorizes, denies or the code expires
+    ///
+    /// Slow down requests from Twitch increase the interval of the following polls.
+    pub fn device_flow_stream(
+        &self,
+        device_code: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = DeviceFlowEvent> + Send + 'static {
+        let state = Some((self.clone(), device_code.to_string(), interval));
+        stream::unfold(state, |state| async move {
+            let (client, device_code, mut interval) = state?;
+            DefaultBackend::sleep(interval).await;
+
+            let event = match client.poll_device_token(&device_code).await {
+                Ok(token) => return Some((DeviceFlowEvent::Authorized(token), None)),
+                Err(DeviceFlowError::AuthorizationPending) => DeviceFlowEvent::Pending,
+                Err(DeviceFlowError::SlowDown { interval: new }) => {
+                    interval = match new {
+                        Some(secs) => Duration::from_secs(secs as u64),
+                        None => interval + Duration::from_secs(5),
+                    };
+                    DeviceFlowEvent::SlowDown { interval }
+                }
+                Err(DeviceFlowError::AccessDenied) => return Some((DeviceFlowEvent::Denied, None)),
+                Err(DeviceFlowError::ExpiredToken) => {
+                    return Some((DeviceFlowEvent::Expired, None))
+                }
+                Err(DeviceFlowError::Other(e)) => return Some((DeviceFlowEvent::Error(e), None)),
+            };
+
+            Some((event, Some((client, device_code, interval))))
+        })
+    }
+}
+
+/// To start the device code flow, you need to provide your client_id as well as a scope array
+///
+/// The user then visits `verification_uri` and enters `user_code`.
+///
+/// ```rust
+/// let scopes = vec!["user:read:email".to_string()];
+/// let device = twitch_oauth_async_std::start_device_flow("client_id", &scopes);
+/// ```
+#[cfg(feature = "client")]
+pub async fn start_device_flow(
+    client_id: &str,
+    scopes: &[String],
+) -> Result<DeviceCodeResponse, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, "")
+        .start_device_flow(scopes)
+        .await
+}
+
+/// To poll for a device flow token, you need to provide your client_id and the device_code
+///
+/// ```rust
+/// use twitch_oauth_async_std::DeviceFlowError;
+///
+/// # async fn run() {
+/// match twitch_oauth_async_std::poll_device_token("client_id", "device_code").await {
+///     Ok(token) => println!("{}", token),
+///     Err(DeviceFlowError::AuthorizationPending) => {}
+///     Err(DeviceFlowError::SlowDown { interval }) => println!("{:?}", interval),
+///     Err(e) => println!("{}", e),
+/// }
+/// # }
+/// ```
+#[cfg(feature = "client")]
+pub async fn poll_device_token(
+    client_id: &str,
+    device_code: &str,
+) -> Result<UserAccessToken, DeviceFlowError> {
+    TwitchOAuthClient::new(client_id, "")
+        .poll_device_token(device_code)
+        .await
+}
+
+/// To poll a device flow until it completes, you need to provide your client_id, the device_code and the polling interval
+///
+/// ```rust
+/// use futures_util::StreamExt;
+/// use std::time::Duration;
+/// use twitch_oauth_async_std::DeviceFlowEvent;
+///
+/// # async fn run() {
+/// let mut events = Box::pin(twitch_oauth_async_std::device_flow_stream(
+///     "client_id",
+///     "device_code",
+///     Duration::from_secs(5),
+/// ));
+/// while let Some(event) = events.next().await {
+///     match event {
+///         DeviceFlowEvent::Pending | DeviceFlowEvent::SlowDown { .. } => {}
+///         DeviceFlowEvent::Authorized(token) => println!("{}", token),
+///         DeviceFlowEvent::Denied | DeviceFlowEvent::Expired => println!("not authorized"),
+///         DeviceFlowEvent::Error(e) => println!("{}", e),
+///     }
+/// }
+/// # }
+/// ```
+#[cfg(feature = "client")]
+pub fn device_flow_stream(
+    client_id: &str,
+    device_code: &str,
+    interval: Duration,
+) -> impl Stream<Item = DeviceFlowEvent> + Send + 'static {
+    TwitchOAuthClient::new(client_id, "").device_flow_stream(device_code, interval)
+}
@@ -0,0 +1,60 @@
+//! The urls of the Twitch OAuth endpoints
+
+/// The url of every endpoint the client talks to, to point each one somewhere else independently
+///
+/// ```rust
+/// use twitch_oauth_async_std::Endpoints;
+///
+/// let endpoints = Endpoints {
+///     token: "http://127.0.0.1:8081/oauth2/token".to_string(),
+///     ..Endpoints::twitch()
+/// };
+/// assert_eq!(endpoints.validate, "https://id.twitch.tv/oauth2/validate");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub token: String,
+    pub validate: String,
+    pub revoke: String,
+    /// Where the user is sent to authorize, see [`TwitchOAuthClient::authorize_url`](crate::TwitchOAuthClient::authorize_url)
+    /// and [`TwitchOAuthClient::oidc_authorize_url`](crate::TwitchOAuthClient::oidc_authorize_url)
+    pub authorize: String,
+    pub device: String,
+    pub userinfo: String,
+    /// The OpenID Connect discovery document
+    pub openid_configuration: String,
+}
+
+impl Endpoints {
+    /// The endpoints of `https://id.twitch.tv`
+    pub fn twitch() -> Endpoints {
+        Endpoints::with_base_url("https://id.twitch.tv")
+    }
+
+    /// The endpoints under `base_url`, at the same paths as on Twitch
+    ///
+    /// ```rust
+    /// let endpoints = twitch_oauth_async_std::Endpoints::with_base_url("http://127.0.0.1:8080/");
+    /// assert_eq!(endpoints.token, "http://127.0.0.1:8080/oauth2/token");
+    /// ```
+    pub fn with_base_url(base_url: &str) -> Endpoints {
+        let base_url = base_url.trim_end_matches('/');
+        let endpoint = |path: &str| format!("{}/oauth2/{}", base_url, path);
+
+        Endpoints {
+            token: endpoint("token"),
+            validate: endpoint("validate"),
+            revoke: endpoint("revoke"),
+            authorize: endpoint("authorize"),
+            device: endpoint("device"),
+            userinfo: endpoint("userinfo"),
+            openid_configuration: endpoint(".well-known/openid-configuration"),
+        }
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::twitch()
+    }
+}
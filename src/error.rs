@@ -0,0 +1,273 @@
+//! The errors returned by the requests
+
+use crate::validate::IncompleteUser;
+#[cfg(feature = "client")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "client")]
+use serde::Deserialize;
+use std::fmt;
+
+/// Errors returned by the Twitch OAuth requests
+#[derive(Debug)]
+pub enum TwitchOAuthError {
+    /// The HTTP request failed
+    #[cfg(feature = "client")]
+    Http(surf::Error),
+    /// The response body could not be deserialized, `body` holds what Twitch sent
+    Deserialize {
+        source: serde_json::Error,
+        body: String,
+        status: u16,
+    },
+    /// Twitch answered with an error
+    Api { status: u16, message: String },
+    /// The token is invalid or expired, `reason` holds the `error` of the `WWW-Authenticate` header
+    /// when Twitch sent one, e.g. `invalid_token` or `insufficient_scope`
+    InvalidToken { reason: Option<String> },
+    /// Twitch rejected the refresh token
+    InvalidRefreshToken,
+    /// The request did not complete within the client timeout
+    Timeout,
+    /// The request, retries and the waits between them included, did not complete within the client deadline
+    DeadlineExceeded,
+    /// The token store has no token to manage
+    NoStoredToken,
+    /// Another call is exchanging the same authorization code, or exchanged it with another
+    /// redirect_uri or code_verifier, see `TwitchOAuthClient::with_code_exchange_guard`
+    CodeAlreadyExchanged,
+    /// The underlying HTTP client could not be configured
+    ClientBuild(String),
+    /// The id_token is not a well formed JWT
+    InvalidIdToken(String),
+    /// Twitch validated a token with only one of `login` and `user_id`, see `ValidatedTokenKind`
+    IncompleteUser {
+        login: Option<String>,
+        user_id: Option<String>,
+    },
+    /// The token was issued to another app than the configured client_id
+    ClientIdMismatch { expected: String, actual: String },
+    /// The request url could not be built, e.g. from an invalid base url
+    UrlBuild(url::ParseError),
+    /// The redirect_uri is not an absolute http or https url, Twitch would reject the authorization
+    InvalidRedirectUri(String),
+    /// The environment variables named here are not set or not valid unicode
+    MissingEnvVar(Vec<String>),
+    /// Twitch answered with something else than JSON, e.g. an HTML page during an outage,
+    /// `body_snippet` holds the start of the body
+    UnexpectedContentType {
+        content_type: String,
+        body_snippet: String,
+        status: u16,
+    },
+}
+
+impl fmt::Display for TwitchOAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "client")]
+            TwitchOAuthError::Http(e) => write!(f, "http error: {}", e),
+            TwitchOAuthError::Deserialize {
+                source,
+                body,
+                status,
+            } => write!(
+                f,
+                "deserialize error: {} (status {}, body: {})",
+                source, status, body
+            ),
+            TwitchOAuthError::Api { status, message } => {
+                write!(f, "twitch api error {}: {}", status, message)
+            }
+            TwitchOAuthError::InvalidToken { reason: None } => write!(f, "invalid token"),
+            TwitchOAuthError::InvalidToken {
+                reason: Some(reason),
+            } => write!(f, "invalid token: {}", reason),
+            TwitchOAuthError::InvalidRefreshToken => write!(f, "invalid refresh token"),
+            TwitchOAuthError::Timeout => write!(f, "request timed out"),
+            TwitchOAuthError::DeadlineExceeded => write!(f, "request deadline exceeded"),
+            TwitchOAuthError::NoStoredToken => write!(f, "no stored token"),
+            TwitchOAuthError::CodeAlreadyExchanged => {
+                write!(f, "authorization code already exchanged or being exchanged")
+            }
+            TwitchOAuthError::ClientBuild(e) => write!(f, "client build error: {}", e),
+            TwitchOAuthError::InvalidIdToken(e) => write!(f, "invalid id_token: {}", e),
+            TwitchOAuthError::IncompleteUser { login, user_id } => write!(
+                f,
+                "validated token with login {:?} and user_id {:?}, expected both or neither",
+                login, user_id
+            ),
+            TwitchOAuthError::ClientIdMismatch { expected, actual } => write!(
+                f,
+                "token issued to client_id {}, expected {}",
+                actual, expected
+            ),
+            TwitchOAuthError::UrlBuild(e) => write!(f, "url build error: {}", e),
+            TwitchOAuthError::InvalidRedirectUri(uri) => write!(
+                f,
+                "invalid redirect_uri {}, expected an absolute http or https url",
+                uri
+            ),
+            TwitchOAuthError::MissingEnvVar(names) => {
+                write!(f, "missing environment variables: {}", names.join(", "))
+            }
+            TwitchOAuthError::UnexpectedContentType {
+                content_type,
+                body_snippet,
+                status,
+            } => write!(
+                f,
+                "unexpected content type {} (status {}, body: {})",
+                content_type, status, body_snippet
+            ),
+        }
+    }
+}
+
+impl TwitchOAuthError {
+    /// The HTTP status Twitch answered with, when the error comes from a response
+    ///
+    /// The status of every response, successful or not, is also passed to the hook of
+    /// `TwitchOAuthClient::with_metrics` along the endpoint it belongs to.
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::TwitchOAuthError;
+    ///
+    /// let err = TwitchOAuthError::Api { status: 400, message: "invalid client".to_string() };
+    /// assert_eq!(err.status(), Some(400));
+    /// assert_eq!(TwitchOAuthError::InvalidToken { reason: None }.status(), Some(401));
+    /// assert_eq!(TwitchOAuthError::Timeout.status(), None);
+    /// ```
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            TwitchOAuthError::Deserialize { status, .. }
+            | TwitchOAuthError::Api { status, .. }
+            | TwitchOAuthError::UnexpectedContentType { status, .. } => Some(*status),
+            TwitchOAuthError::InvalidToken { .. } => Some(401),
+            TwitchOAuthError::InvalidRefreshToken => Some(400),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for TwitchOAuthError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "client")]
+            TwitchOAuthError::Http(e) => Some(e.as_ref()),
+            TwitchOAuthError::Deserialize { source, .. } => Some(source),
+            TwitchOAuthError::UrlBuild(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<surf::Error> for TwitchOAuthError {
+    fn from(e: surf::Error) -> Self {
+        TwitchOAuthError::Http(e)
+    }
+}
+
+impl From<IncompleteUser> for TwitchOAuthError {
+    fn from(e: IncompleteUser) -> Self {
+        TwitchOAuthError::IncompleteUser {
+            login: e.login,
+            user_id: e.user_id,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+pub(crate) fn invalid_token(res: &surf::Response) -> TwitchOAuthError {
+    TwitchOAuthError::InvalidToken {
+        reason: res
+            .header("www-authenticate")
+            .and_then(|header| www_authenticate_reason(header.as_str())),
+    }
+}
+
+// `Bearer realm="twitch", error="insufficient_scope"` gives `insufficient_scope`
+#[cfg(feature = "client")]
+fn www_authenticate_reason(header: &str) -> Option<String> {
+    let params = header.split_once(' ').map_or(header, |(_, params)| params);
+    params.split(',').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("error") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+// How much of a non-JSON body is kept in `UnexpectedContentType`
+#[cfg(feature = "client")]
+const BODY_SNIPPET_LEN: usize = 200;
+
+/// Read the body, refusing it when Twitch says it isn't JSON
+///
+/// A response without a `Content-Type` is let through, to not reject an empty body.
+#[cfg(feature = "client")]
+pub(crate) async fn read_body(res: &mut surf::Response) -> Result<String, TwitchOAuthError> {
+    let body = res.body_string().await?;
+    if let Some(content_type) = res.header("content-type") {
+        let content_type = content_type.as_str();
+        if !content_type.to_ascii_lowercase().contains("json") {
+            return Err(TwitchOAuthError::UnexpectedContentType {
+                content_type: content_type.to_string(),
+                body_snippet: body.chars().take(BODY_SNIPPET_LEN).collect(),
+                status: res.status().into(),
+            });
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(feature = "client")]
+pub(crate) async fn read_json<T: DeserializeOwned>(
+    res: &mut surf::Response,
+) -> Result<T, TwitchOAuthError> {
+    let body = read_body(res).await?;
+    if !res.status().is_success() {
+        return Err(TwitchApiError::parse(res.status(), &body).into());
+    }
+    serde_json::from_str(&body).map_err(|source| TwitchOAuthError::Deserialize {
+        source,
+        status: res.status().into(),
+        body,
+    })
+}
+
+/// The error body Twitch sends on non-2xx responses, e.g. `{"status":400,"message":"invalid client"}`
+#[cfg(feature = "client")]
+#[derive(Debug, Deserialize)]
+pub(crate) struct TwitchApiError {
+    #[serde(default)]
+    status: u16,
+    pub(crate) message: String,
+    pub(crate) interval: Option<usize>,
+}
+
+#[cfg(feature = "client")]
+impl TwitchApiError {
+    // Falls back to the raw body when it isn't a Twitch error object
+    pub(crate) fn parse(status: surf::StatusCode, body: &str) -> TwitchApiError {
+        let mut err = serde_json::from_str(body).unwrap_or_else(|_| TwitchApiError {
+            status: 0,
+            message: body.to_string(),
+            interval: None,
+        });
+        err.status = status.into();
+        err
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<TwitchApiError> for TwitchOAuthError {
+    fn from(e: TwitchApiError) -> Self {
+        TwitchOAuthError::Api {
+            status: e.status,
+            message: e.message,
+        }
+    }
+}
@@ -1,137 +1,78 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fmt;
-use surf::RequestBuilder;
-use url::Url;
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ValidatedToken {
-    pub client_id: String,
-    pub login: Option<String>,
-    pub user_id: Option<String>,
-    pub scopes: Vec<String>,
-}
-
-// To use the `{}` marker, the trait `fmt::Display` must be implemented
-// manually for the type.
-impl fmt::Display for ValidatedToken {
-    // This trait requires `fmt` with this exact signature.
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let string1 = format!("access_token: {}\n", self.client_id);
-        let string2 = format!("{} token_type: {:?}\n", string1, self.login);
-        let string3 = format!("{} expires_in: {:?}\n", string2, self.user_id);
-        let string4 = format!("{} expires_in: {:?}\n", string3, self.scopes);
-
-        write!(f, "{}", string4)
-    }
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AppAccessToken {
-    pub access_token: String,
-    pub expires_in: usize,
-    pub scope: Option<Vec<String>>,
-    pub token_type: String,
-}
-// To use the `{}` marker, the trait `fmt::Display` must be implemented
-// manually for the type.
-impl fmt::Display for AppAccessToken {
-    // This trait requires `fmt` with this exact signature.
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let string1 = format!("access_token: {}\n", self.access_token);
-        let string2 = format!("{} expires_in: {}\n", string1, self.expires_in);
-        let string3 = format!("{} expires_in: {:?}\n", string2, self.scope);
-        let string4 = format!("{} token_type: {}\n", string3, self.token_type);
-        write!(f, "{}", string4)
-    }
-}
-
-/// To retrieve a token, you need to provide your client_id and client_secret as well as a scope array
-///
-/// ```rust
-/// let token = twitch_oauth_async_std::get_app_access_token("client_id", "client_secret");
-/// ```
-pub async fn get_app_access_token(
-    client_id: &str,
-    client_secret: &str,
-) -> Result<AppAccessToken, Box<dyn std::error::Error>> {
-    let mut params = HashMap::new();
-    params.insert("grant_type", "client_credentials");
-    params.insert("client_id", client_id);
-    params.insert("client_secret", client_secret);
-    let url = Url::parse_with_params("https://id.twitch.tv/oauth2/token", &params).unwrap();
-
-    let client = surf::Client::new();
-    let req = client.post(&url);
-    let mut res = client.send(req).await?;
-    let resp: AppAccessToken = res.body_json().await?;
-
-    Ok(resp)
-}
-/// To retrieve a token, you need to provide your client_id and client_secret as well as a scope array
-///
-/// ```rust
-/// let token = twitch_oauth_async_std::get_app_access_token_with_scopes("client_id", "client_secret", vec!["scopes".to_string()]);
-/// ```
-pub async fn get_app_access_token_with_scopes(
-    client_id: &str,
-    client_secret: &str,
-    scopes: Vec<String>,
-) -> Result<AppAccessToken, Box<dyn std::error::Error>> {
-    let joinee_scopes = scopes.join(" ");
-
-    let mut params = HashMap::new();
-    params.insert("grant_type", "client_credentials");
-    params.insert("client_id", client_id);
-    params.insert("client_secret", client_secret);
-    params.insert("scope", joinee_scopes.as_str());
-    let url = Url::parse_with_params("https://id.twitch.tv/oauth2/token", &params).unwrap();
-
-    let client = surf::Client::new();
-    let req = client.post(&url);
-    let mut res = client.send(req).await?;
-    let resp: AppAccessToken = res.body_json().await?;
-
-    Ok(resp)
-}
-
-/// To validate a token, you need to provide your access token
-///
-/// ```rust
-/// let token = twitch_oauth_async_std::validate_token("access_token");
-/// ```
-pub async fn validate_token(
-    access_token: &str,
-) -> Result<ValidatedToken, Box<dyn std::error::Error>> {
-    let auth = format!("OAuth {}", access_token);
-
-    let client = surf::Client::new();
-    let req: RequestBuilder = client
-        .get("https://id.twitch.tv/oauth2/validate")
-        .header("authorization", auth);
-    let mut res = client.send(req).await?;
-    let resp: ValidatedToken = res.body_json().await?;
-
-    Ok(resp)
-}
-
-/// To remoke a token, you need to provide your access token and client_id
-///
-/// ```rust
-/// let token = twitch_oauth_async_std::remoke_token("token", "client_id");
-/// ```
-pub async fn remoke_token(
-    access_token: &str,
-    client_id: &str,
-) -> Result<surf::StatusCode, Box<dyn std::error::Error>> {
-    let mut params = HashMap::new();
-    params.insert("token", access_token);
-    params.insert("client_id", client_id);
-
-    let url = Url::parse_with_params("https://id.twitch.tv/oauth2/revoke", &params).unwrap();
-
-    let client = surf::Client::new();
-    let req: RequestBuilder = client.post(&url);
-    let res = client.send(req).await?;
-    Ok(res.status())
-}
+#[cfg(all(feature = "client", not(any(feature = "isahc", feature = "reqwest"))))]
+compile_error!("the `client` feature needs an HTTP backend, enable `isahc` or `reqwest`");
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+mod app_token;
+mod authorize;
+#[cfg(feature = "client")]
+mod backend;
+#[cfg(feature = "client")]
+mod client;
+mod clock;
+mod device;
+mod endpoints;
+mod error;
+mod oidc;
+#[cfg(feature = "client")]
+mod revoke;
+#[cfg(feature = "client")]
+mod rsa;
+mod scope;
+#[cfg(feature = "client")]
+mod session;
+mod stored;
+mod token;
+mod user_token;
+mod validate;
+
+#[cfg(feature = "client")]
+pub use async_trait::async_trait;
+
+pub use app_token::AppAccessToken;
+#[cfg(feature = "client")]
+pub use app_token::{
+    authorize_request, get_app_access_token, get_app_access_token_validated,
+    get_app_access_token_with_params, get_app_access_token_with_scopes,
+    get_app_access_token_with_typed_scopes, AppTokenCache, AppTokenRequest,
+};
+pub use authorize::{
+    build_authorize_url, build_authorize_url_pkce, build_authorize_url_token,
+    build_authorize_url_with, build_incremental_authorize_url, generate_state,
+    generate_state_from_rng, parse_callback, parse_implicit_fragment, verify_state, AuthCallback,
+    AuthorizeOptions, AuthorizeResponseType, AuthorizeUrl, CallbackError, ImplicitToken, Pkce,
+};
+#[cfg(feature = "client")]
+pub use client::{BatchSummary, RateLimit, RequestMetrics, TwitchOAuthClient};
+pub use clock::{Clock, MockClock, SystemClock};
+#[cfg(feature = "client")]
+pub use device::{device_flow_stream, poll_device_token, start_device_flow};
+pub use device::{DeviceCodeResponse, DeviceFlowError, DeviceFlowEvent};
+pub use endpoints::Endpoints;
+pub use error::TwitchOAuthError;
+pub use oidc::{
+    build_authorize_url_oidc, parse_hybrid_fragment, HybridCallback, IdToken, IdTokenClaims,
+    OidcAuthorizeUrl, OpenIdConfiguration, ResponseType, UserInfo,
+};
+#[cfg(feature = "client")]
+pub use oidc::{
+    exchange_hybrid, get_openid_configuration, get_userinfo, verify_id_token,
+    verify_id_token_with_nonce, TokenVerificationError,
+};
+#[cfg(feature = "client")]
+#[allow(deprecated)]
+pub use revoke::{remoke_token, revoke_token, revoke_tokens, RevokeOutcome};
+pub use scope::{Scope, ScopeSet, UnknownScope};
+#[cfg(feature = "client")]
+pub use session::{AuthSession, AuthSessionError};
+pub use stored::StoredToken;
+pub use user_token::UserAccessToken;
+#[cfg(feature = "client")]
+pub use user_token::{
+    exchange_code, exchange_code_pkce, refresh_token, MemoryTokenStore, TokenStore,
+    UserTokenManager,
+};
+#[cfg(feature = "client")]
+pub use validate::validate_token;
+pub use validate::{IncompleteUser, MissingScopes, ScopeDiff, ValidatedToken, ValidatedTokenKind};
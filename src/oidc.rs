@@ -0,0 +1,792 @@
+//! OpenID Connect id_tokens, userinfo and discovery
+
+#[cfg(feature = "client")]
+use crate::authorize::verify_state;
+use crate::authorize::{authorize_endpoint, check_redirect_uri, CallbackError};
+#[cfg(feature = "client")]
+use crate::client::TwitchOAuthClient;
+use crate::error::TwitchOAuthError;
+#[cfg(feature = "client")]
+use crate::error::{invalid_token, read_json};
+use crate::scope::{join_scopes, normalize_scopes, split_scopes};
+#[cfg(feature = "client")]
+use crate::token::bearer_header;
+#[cfg(feature = "client")]
+use crate::user_token::UserAccessToken;
+#[cfg(feature = "client")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "client")]
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+#[cfg(feature = "client")]
+use std::fmt;
+#[cfg(feature = "client")]
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// The claims of an OpenID Connect id_token
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    /// The client_ids the token is issued to, Twitch sends a single one but a list is valid too
+    #[serde(deserialize_with = "deserialize_audience")]
+    pub aud: Vec<String>,
+    pub exp: u64,
+    pub iat: u64,
+    pub nonce: Option<String>,
+    /// The hash of the code returned along the token in a hybrid flow redirect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_hash: Option<String>,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// `aud` is either a single client_id or a list of them
+fn deserialize_audience<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Audience {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Audience::deserialize(deserializer)? {
+        Audience::One(aud) => vec![aud],
+        Audience::Many(aud) => aud,
+    })
+}
+
+/// The claims returned by the OpenID Connect userinfo endpoint
+///
+/// Only `sub` is always present, the others depend on the requested scopes and claims.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserInfo {
+    pub sub: String,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// The OpenID Connect discovery document, listing the endpoints and capabilities of Twitch
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub response_types_supported: Vec<String>,
+    #[serde(default)]
+    pub subject_types_supported: Vec<String>,
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    #[serde(default)]
+    pub claims_supported: Vec<String>,
+    #[serde(default)]
+    pub claims_parameter_supported: bool,
+}
+
+/// An OpenID Connect id_token JWT
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct IdToken(pub String);
+
+impl IdToken {
+    /// Decode the claims of the token without verifying its signature
+    ///
+    /// Do not base authorization decisions on unverified claims.
+    ///
+    /// ```rust
+    /// let payload = base64::encode_config(
+    ///     r#"{"iss":"https://id.twitch.tv/oauth2","sub":"1","aud":"client_id","exp":1,"iat":0,"preferred_username":"login"}"#,
+    ///     base64::URL_SAFE_NO_PAD,
+    /// );
+    /// let token = twitch_oauth_async_std::IdToken(format!("header.{}.signature", payload));
+    /// let claims = token.decode_unverified().unwrap();
+    /// assert_eq!(claims.sub, "1");
+    /// assert_eq!(claims.preferred_username.as_deref(), Some("login"));
+    /// ```
+    pub fn decode_unverified(&self) -> Result<IdTokenClaims, TwitchOAuthError> {
+        let payload = self
+            .0
+            .split('.')
+            .nth(1)
+            .ok_or_else(|| TwitchOAuthError::InvalidIdToken("missing payload".to_string()))?;
+        let json = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| TwitchOAuthError::InvalidIdToken(e.to_string()))?;
+
+        serde_json::from_slice(&json).map_err(|e| TwitchOAuthError::InvalidIdToken(e.to_string()))
+    }
+}
+
+/// The id_token and code of a hybrid flow redirect, parsed from the redirect fragment
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HybridCallback {
+    pub code: String,
+    pub id_token: IdToken,
+    pub scope: Vec<String>,
+    pub state: String,
+}
+
+/// To parse the redirect of a [`ResponseType::CodeIdToken`] flow, you need to provide the fragment of the redirect url, with or without the leading `#`
+///
+/// The id_token identifies the user right away, verify it before trusting it, e.g. with
+/// [`TwitchOAuthClient::exchange_hybrid`] which also exchanges the code.
+///
+/// ```rust
+/// let callback = twitch_oauth_async_std::parse_hybrid_fragment(
+///     "#code=abc&id_token=header.payload.signature&scope=openid+chat%3Aread&state=xyz",
+/// )
+/// .unwrap();
+/// assert_eq!(callback.code, "abc");
+/// assert_eq!(callback.id_token.0, "header.payload.signature");
+/// assert_eq!(callback.scope, vec!["openid", "chat:read"]);
+/// assert_eq!(callback.state, "xyz");
+///
+/// assert!(twitch_oauth_async_std::parse_hybrid_fragment("code=abc&state=xyz").is_err());
+/// ```
+pub fn parse_hybrid_fragment(fragment: &str) -> Result<HybridCallback, CallbackError> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let params: HashMap<String, String> = url::form_urlencoded::parse(fragment.as_bytes())
+        .into_owned()
+        .collect();
+
+    if let Some(error) = params.get("error") {
+        return Err(CallbackError::Denied {
+            error: error.to_string(),
+            error_description: params.get("error_description").cloned(),
+        });
+    }
+
+    let code = params
+        .get("code")
+        .ok_or(CallbackError::MissingParameter("code"))?;
+    let id_token = params
+        .get("id_token")
+        .ok_or(CallbackError::MissingParameter("id_token"))?;
+    let state = params
+        .get("state")
+        .ok_or(CallbackError::MissingParameter("state"))?;
+    let scope = params
+        .get("scope")
+        .map(|scope| split_scopes(scope))
+        .unwrap_or_default();
+
+    Ok(HybridCallback {
+        code: code.to_string(),
+        id_token: IdToken(id_token.to_string()),
+        scope,
+        state: state.to_string(),
+    })
+}
+
+/// Errors returned while verifying an id_token
+#[cfg(feature = "client")]
+#[derive(Debug)]
+pub enum TokenVerificationError {
+    /// The token is not a well formed JWT
+    Malformed(String),
+    /// The token is not signed with RS256
+    UnsupportedAlgorithm(String),
+    /// No key of the JWKS matches the `kid` of the token
+    UnknownKey(Option<String>),
+    /// The signature doesn't match the token
+    InvalidSignature,
+    /// The `iss` claim isn't the Twitch issuer
+    InvalidIssuer(String),
+    /// The `aud` claim doesn't contain your client_id
+    InvalidAudience(Vec<String>),
+    /// The `exp` claim is in the past
+    Expired,
+    /// The `nonce` claim, if any, isn't the nonce sent with the authorization request
+    InvalidNonce(Option<String>),
+    /// The `c_hash` claim, if any, isn't the hash of the code returned along the token
+    InvalidCodeHash(Option<String>),
+    /// The discovery document or the JWKS could not be fetched
+    Request(TwitchOAuthError),
+}
+
+#[cfg(feature = "client")]
+impl fmt::Display for TokenVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenVerificationError::Malformed(e) => write!(f, "malformed id_token: {}", e),
+            TokenVerificationError::UnsupportedAlgorithm(alg) => {
+                write!(f, "unsupported algorithm: {}", alg)
+            }
+            TokenVerificationError::UnknownKey(kid) => write!(f, "unknown key: {:?}", kid),
+            TokenVerificationError::InvalidSignature => write!(f, "invalid signature"),
+            TokenVerificationError::InvalidIssuer(iss) => write!(f, "invalid issuer: {}", iss),
+            TokenVerificationError::InvalidAudience(aud) => {
+                write!(f, "invalid audience: {}", aud.join(" "))
+            }
+            TokenVerificationError::Expired => write!(f, "id_token expired"),
+            TokenVerificationError::InvalidNonce(nonce) => write!(f, "invalid nonce: {:?}", nonce),
+            TokenVerificationError::InvalidCodeHash(c_hash) => {
+                write!(f, "invalid c_hash: {:?}", c_hash)
+            }
+            TokenVerificationError::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl std::error::Error for TokenVerificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TokenVerificationError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<TwitchOAuthError> for TokenVerificationError {
+    fn from(e: TwitchOAuthError) -> Self {
+        TokenVerificationError::Request(e)
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Deserialize, Clone)]
+struct Jwk {
+    kid: Option<String>,
+    n: String,
+    e: String,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub(crate) struct JwksCache {
+    issuer: String,
+    keys: Vec<Jwk>,
+    fetched_at: SystemTime,
+}
+
+// How long after fetching the keys a token with an unknown `kid` is rejected without fetching them again
+#[cfg(feature = "client")]
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[cfg(feature = "client")]
+impl JwksCache {
+    // Without a `kid` in the token, the first key is tried
+    fn key(&self, kid: &Option<String>) -> Option<Jwk> {
+        self.keys
+            .iter()
+            .find(|key| kid.is_none() || key.kid == *kid)
+            .cloned()
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+// The `c_hash` of a code in an RS256 token, the left half of its SHA-256
+#[cfg(feature = "client")]
+fn code_hash(code: &str) -> String {
+    let digest = Sha256::digest(code.as_bytes());
+    base64::encode_config(&digest[..16], base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(feature = "client")]
+fn decode_segment(segment: &str) -> Result<Vec<u8>, TokenVerificationError> {
+    base64::decode_config(segment, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| TokenVerificationError::Malformed(e.to_string()))
+}
+
+#[cfg(feature = "client")]
+fn decode_json_segment<T: DeserializeOwned>(segment: &str) -> Result<T, TokenVerificationError> {
+    serde_json::from_slice(&decode_segment(segment)?)
+        .map_err(|e| TokenVerificationError::Malformed(e.to_string()))
+}
+
+#[cfg(feature = "client")]
+impl TwitchOAuthClient {
+    /// Retrieve the OpenID Connect claims of the user an access token belongs to
+    ///
+    /// An invalid or expired token is surfaced as [`TwitchOAuthError::InvalidToken`].
+    pub async fn userinfo(&self, access_token: &str) -> Result<UserInfo, TwitchOAuthError> {
+        let auth = bearer_header(access_token);
+        let url = Url::parse(&self.endpoints.userinfo).map_err(TwitchOAuthError::UrlBuild)?;
+
+        let mut res = self
+            .send_with_retries(|| self.client.get(&url).header("authorization", auth.as_str()))
+            .await?;
+        if res.status() == surf::StatusCode::Unauthorized {
+            return Err(invalid_token(&res));
+        }
+        let resp: UserInfo = read_json(&mut res).await?;
+
+        Ok(resp)
+    }
+
+    /// Verify the signature, `iss`, `aud` and `exp` of an id_token issued to this client_id
+    ///
+    /// `aud` may list several client_ids, one of them must be this one. To also check the nonce of a
+    /// token received in a redirect, use [`TwitchOAuthClient::verify_id_token_with_nonce`].
+    ///
+    /// The signing keys are fetched from the `jwks_uri` of [`TwitchOAuthClient::openid_configuration`], then cached
+    /// and shared between clones of this client, see [`TwitchOAuthClient::with_jwks_cache_ttl`]. A token
+    /// signed with a key missing from the cache fetches the keys again, as Twitch may have rotated them,
+    /// at most once a minute so forged tokens can't make the client hammer Twitch.
+    pub async fn verify_id_token(
+        &self,
+        id_token: &str,
+    ) -> Result<IdTokenClaims, TokenVerificationError> {
+        self.verify_id_token_claims(id_token, None).await
+    }
+
+    /// Verify an id_token like [`TwitchOAuthClient::verify_id_token`], and that its `nonce` is the one sent to authorize
+    ///
+    /// Use it for every id_token received in a redirect, as its nonce ties it to the authorization
+    /// request, so a token replayed from another login is rejected.
+    pub async fn verify_id_token_with_nonce(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, TokenVerificationError> {
+        self.verify_id_token_claims(id_token, Some(nonce)).await
+    }
+
+    async fn verify_id_token_claims(
+        &self,
+        id_token: &str,
+        nonce: Option<&str>,
+    ) -> Result<IdTokenClaims, TokenVerificationError> {
+        let parts: Vec<&str> = id_token.split('.').collect();
+        let (header, payload, signature) = match parts.as_slice() {
+            [header, payload, signature] => (*header, *payload, *signature),
+            _ => {
+                return Err(TokenVerificationError::Malformed(
+                    "expected three segments".to_string(),
+                ))
+            }
+        };
+
+        let jwt_header: JwtHeader = decode_json_segment(header)?;
+        if jwt_header.alg != "RS256" {
+            return Err(TokenVerificationError::UnsupportedAlgorithm(jwt_header.alg));
+        }
+
+        let mut jwks = self.jwks(false).await?;
+        let mut key = jwks.key(&jwt_header.kid);
+        if key.is_none() && self.clock().now() >= jwks.fetched_at + JWKS_REFETCH_INTERVAL {
+            jwks = self.jwks(true).await?;
+            key = jwks.key(&jwt_header.kid);
+        }
+        let key = key.ok_or_else(|| TokenVerificationError::UnknownKey(jwt_header.kid.clone()))?;
+        let signed = format!("{}.{}", header, payload);
+        if !crate::rsa::verify_rs256(
+            &decode_segment(&key.n)?,
+            &decode_segment(&key.e)?,
+            signed.as_bytes(),
+            &decode_segment(signature)?,
+        ) {
+            return Err(TokenVerificationError::InvalidSignature);
+        }
+
+        let claims: IdTokenClaims = decode_json_segment(payload)?;
+        if claims.iss != jwks.issuer {
+            return Err(TokenVerificationError::InvalidIssuer(claims.iss));
+        }
+        if !claims.aud.contains(&self.client_id) {
+            return Err(TokenVerificationError::InvalidAudience(claims.aud));
+        }
+        let now = self
+            .clock()
+            .now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if claims.exp <= now {
+            return Err(TokenVerificationError::Expired);
+        }
+        if let Some(nonce) = nonce {
+            let received = claims.nonce.as_deref().unwrap_or_default();
+            if !verify_state(nonce, received) {
+                return Err(TokenVerificationError::InvalidNonce(claims.nonce));
+            }
+        }
+
+        Ok(claims)
+    }
+
+    /// Verify the id_token of a hybrid flow redirect, then exchange its code
+    ///
+    /// The id_token must carry the `nonce` sent to authorize and the `c_hash` of the code, so neither
+    /// a token replayed from another login nor a code swapped in from another session is accepted.
+    /// It is verified first, so a forged redirect doesn't use up the code. The redirect_uri must be
+    /// the one used to authorize.
+    pub async fn exchange_hybrid(
+        &self,
+        callback: &HybridCallback,
+        redirect_uri: &str,
+        nonce: &str,
+    ) -> Result<(IdTokenClaims, UserAccessToken), TokenVerificationError> {
+        let claims = self
+            .verify_id_token_with_nonce(&callback.id_token.0, nonce)
+            .await?;
+        let received = claims.c_hash.as_deref().unwrap_or_default();
+        if !verify_state(&code_hash(&callback.code), received) {
+            return Err(TokenVerificationError::InvalidCodeHash(claims.c_hash));
+        }
+        let token = self.exchange_code(&callback.code, redirect_uri).await?;
+
+        Ok((claims, token))
+    }
+
+    /// Retrieve the OpenID Connect discovery document
+    pub async fn openid_configuration(&self) -> Result<OpenIdConfiguration, TwitchOAuthError> {
+        let url =
+            Url::parse(&self.endpoints.openid_configuration).map_err(TwitchOAuthError::UrlBuild)?;
+
+        let mut res = self.send_with_retries(|| self.client.get(&url)).await?;
+        let resp: OpenIdConfiguration = read_json(&mut res).await?;
+
+        Ok(resp)
+    }
+
+    /// Build an OpenID Connect authorization url against the authorize endpoint of this client
+    ///
+    /// Like [`TwitchOAuthClient::authorize_url`], it fails with [`TwitchOAuthError::UrlBuild`] when
+    /// that endpoint isn't a valid url.
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{build_authorize_url_oidc, ResponseType, TwitchOAuthClient};
+    ///
+    /// let client = TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_base_url("http://127.0.0.1:8080");
+    /// let oidc = build_authorize_url_oidc(
+    ///     "client_id",
+    ///     "http://localhost:3000/callback",
+    ///     &[],
+    ///     "state",
+    ///     "nonce",
+    ///     ResponseType::Code,
+    /// );
+    /// let url = client.oidc_authorize_url(&oidc).unwrap();
+    /// assert_eq!(url.path(), "/oauth2/authorize");
+    /// assert_eq!(url.port(), Some(8080));
+    /// ```
+    pub fn oidc_authorize_url(&self, url: &OidcAuthorizeUrl) -> Result<Url, TwitchOAuthError> {
+        let endpoint = Url::parse(&self.endpoints.authorize).map_err(TwitchOAuthError::UrlBuild)?;
+        url.build_at(endpoint)
+    }
+
+    // `refresh` skips the cache, e.g. when it lacks the key a token was signed with
+    async fn jwks(&self, refresh: bool) -> Result<JwksCache, TwitchOAuthError> {
+        if !refresh {
+            let cached = self.jwks.lock().unwrap().clone();
+            if let Some(jwks) =
+                cached.filter(|jwks| self.clock().now() < jwks.fetched_at + self.jwks_cache_ttl)
+            {
+                return Ok(jwks);
+            }
+        }
+
+        let discovery = self.openid_configuration().await?;
+
+        let jwks_url = Url::parse(&discovery.jwks_uri).map_err(TwitchOAuthError::UrlBuild)?;
+        let mut res = self
+            .send_with_retries(|| self.client.get(&jwks_url))
+            .await?;
+        let keys: Jwks = read_json(&mut res).await?;
+
+        let jwks = JwksCache {
+            issuer: discovery.issuer,
+            keys: keys.keys,
+            fetched_at: self.clock().now(),
+        };
+        *self.jwks.lock().unwrap() = Some(jwks.clone());
+
+        Ok(jwks)
+    }
+}
+
+/// To retrieve the OpenID Connect claims of a user, you need to provide their access token
+///
+/// ```rust
+/// let user = twitch_oauth_async_std::get_userinfo("access_token");
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_userinfo(access_token: &str) -> Result<UserInfo, TwitchOAuthError> {
+    TwitchOAuthClient::new("", "").userinfo(access_token).await
+}
+
+/// To retrieve the OpenID Connect discovery document, you don't need to provide anything
+///
+/// ```rust
+/// let configuration = twitch_oauth_async_std::get_openid_configuration();
+/// ```
+#[cfg(feature = "client")]
+pub async fn get_openid_configuration() -> Result<OpenIdConfiguration, TwitchOAuthError> {
+    TwitchOAuthClient::new("", "").openid_configuration().await
+}
+
+/// To verify an id_token, you need to provide the id_token and your client_id
+///
+/// Use [`TwitchOAuthClient::verify_id_token`] to cache the signing keys between verifications.
+///
+/// ```rust
+/// let claims = twitch_oauth_async_std::verify_id_token("id_token", "client_id");
+/// ```
+#[cfg(feature = "client")]
+pub async fn verify_id_token(
+    id_token: &str,
+    client_id: &str,
+) -> Result<IdTokenClaims, TokenVerificationError> {
+    TwitchOAuthClient::new(client_id, "")
+        .verify_id_token(id_token)
+        .await
+}
+
+/// To verify an id_token received in a redirect, you need to provide the id_token, your client_id and the nonce sent to authorize
+///
+/// ```rust
+/// let claims = twitch_oauth_async_std::verify_id_token_with_nonce("id_token", "client_id", "nonce");
+/// ```
+#[cfg(feature = "client")]
+pub async fn verify_id_token_with_nonce(
+    id_token: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, TokenVerificationError> {
+    TwitchOAuthClient::new(client_id, "")
+        .verify_id_token_with_nonce(id_token, nonce)
+        .await
+}
+
+/// To finish a hybrid flow, you need to provide your client_id, client_secret, the parsed redirect and the same redirect_uri and nonce used to authorize
+///
+/// ```rust
+/// let callback = twitch_oauth_async_std::parse_hybrid_fragment("code=abc&id_token=a.b.c&state=xyz").unwrap();
+/// let login = twitch_oauth_async_std::exchange_hybrid(
+///     "client_id",
+///     "client_secret",
+///     &callback,
+///     "http://localhost:3000/callback",
+///     "nonce",
+/// );
+/// ```
+#[cfg(feature = "client")]
+pub async fn exchange_hybrid(
+    client_id: &str,
+    client_secret: &str,
+    callback: &HybridCallback,
+    redirect_uri: &str,
+    nonce: &str,
+) -> Result<(IdTokenClaims, UserAccessToken), TokenVerificationError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .exchange_hybrid(callback, redirect_uri, nonce)
+        .await
+}
+
+/// The `response_type` of an OpenID Connect authorization request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseType {
+    /// An authorization code to exchange, the token response includes an id_token
+    Code,
+    /// An id_token returned directly in the redirect fragment
+    IdToken,
+    /// The hybrid flow, an id_token and a code to exchange both returned in the redirect fragment
+    ///
+    /// Parse the redirect with [`parse_hybrid_fragment`].
+    CodeIdToken,
+}
+
+impl ResponseType {
+    /// The response type as Twitch spells it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseType::Code => "code",
+            ResponseType::IdToken => "id_token",
+            ResponseType::CodeIdToken => "code id_token",
+        }
+    }
+}
+
+/// To start an OpenID Connect flow, you need to provide the same arguments as [`build_authorize_url`](crate::build_authorize_url), a nonce and a [`ResponseType`]
+///
+/// The `openid` scope is added when missing. Call [`OidcAuthorizeUrl::build`] to get the url.
+///
+/// ```rust
+/// use twitch_oauth_async_std::ResponseType;
+///
+/// let url = twitch_oauth_async_std::build_authorize_url_oidc(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &["user:read:email".to_string()],
+///     "state",
+///     "nonce",
+///     ResponseType::IdToken,
+/// )
+/// .build()
+/// .unwrap();
+///
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["response_type"], "id_token");
+/// assert_eq!(params["scope"], "openid user:read:email");
+/// assert_eq!(params["nonce"], "nonce");
+/// assert!(!params.contains_key("claims"));
+///
+/// let url = twitch_oauth_async_std::build_authorize_url_oidc(
+///     "client_id",
+///     "http://localhost:3000/callback",
+///     &[],
+///     "state",
+///     "nonce",
+///     ResponseType::CodeIdToken,
+/// )
+/// .build()
+/// .unwrap();
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["response_type"], "code id_token");
+/// ```
+pub fn build_authorize_url_oidc(
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &[String],
+    state: &str,
+    nonce: &str,
+    response_type: ResponseType,
+) -> OidcAuthorizeUrl {
+    let mut scopes = normalize_scopes(scopes);
+    if !scopes.iter().any(|scope| scope == "openid") {
+        scopes.insert(0, "openid".to_string());
+    }
+
+    OidcAuthorizeUrl {
+        client_id: client_id.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scopes,
+        state: state.to_string(),
+        nonce: nonce.to_string(),
+        response_type,
+        claims: Vec::new(),
+        force_verify: false,
+    }
+}
+
+/// An OpenID Connect authorization url, returned by [`build_authorize_url_oidc`]
+#[derive(Debug, Clone)]
+pub struct OidcAuthorizeUrl {
+    client_id: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    state: String,
+    nonce: String,
+    response_type: ResponseType,
+    claims: Vec<String>,
+    force_verify: bool,
+}
+
+impl OidcAuthorizeUrl {
+    /// Request claims such as `email` or `email_verified`, both in the id_token and from the userinfo endpoint
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::ResponseType;
+    ///
+    /// let url = twitch_oauth_async_std::build_authorize_url_oidc(
+    ///     "client_id",
+    ///     "http://localhost:3000/callback",
+    ///     &[],
+    ///     "state",
+    ///     "nonce",
+    ///     ResponseType::Code,
+    /// )
+    /// .request_claims(&["email", "email_verified"])
+    /// .build()
+    /// .unwrap();
+    ///
+    /// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+    /// assert_eq!(
+    ///     params["claims"],
+    ///     r#"{"id_token":{"email":null,"email_verified":null},"userinfo":{"email":null,"email_verified":null}}"#
+    /// );
+    /// ```
+    pub fn request_claims(mut self, claims: &[&str]) -> Self {
+        for claim in claims {
+            if !self.claims.iter().any(|c| c == claim) {
+                self.claims.push(claim.to_string());
+            }
+        }
+        self
+    }
+
+    /// Force Twitch to prompt the user again even if they already authorized your app, to switch accounts
+    pub fn force_verify(mut self, force_verify: bool) -> Self {
+        self.force_verify = force_verify;
+        self
+    }
+
+    /// Build the authorization url, failing when the redirect_uri isn't an absolute http or https url
+    ///
+    /// The url points at Twitch, see [`TwitchOAuthClient::oidc_authorize_url`] to use the
+    /// endpoints of a client instead.
+    pub fn build(&self) -> Result<Url, TwitchOAuthError> {
+        self.build_at(authorize_endpoint())
+    }
+
+    // `url` is the authorize endpoint without a query, see `TwitchOAuthClient::oidc_authorize_url`
+    pub(crate) fn build_at(&self, mut url: Url) -> Result<Url, TwitchOAuthError> {
+        check_redirect_uri(&self.redirect_uri)?;
+        let joinee_scopes = join_scopes(&self.scopes);
+
+        let mut params = HashMap::new();
+        params.insert("response_type", self.response_type.as_str().to_string());
+        params.insert("client_id", self.client_id.clone());
+        params.insert("redirect_uri", self.redirect_uri.clone());
+        params.insert("scope", joinee_scopes);
+        params.insert("state", self.state.clone());
+        params.insert("nonce", self.nonce.clone());
+        if self.force_verify {
+            params.insert("force_verify", "true".to_string());
+        }
+
+        if !self.claims.is_empty() {
+            let requested: serde_json::Map<String, serde_json::Value> = self
+                .claims
+                .iter()
+                .map(|claim| (claim.clone(), serde_json::Value::Null))
+                .collect();
+            let claims = serde_json::json!({
+                "id_token": requested,
+                "userinfo": requested,
+            });
+            params.insert("claims", claims.to_string());
+        }
+
+        url.query_pairs_mut().extend_pairs(&params);
+        Ok(url)
+    }
+}
+
+impl TryFrom<OidcAuthorizeUrl> for Url {
+    type Error = TwitchOAuthError;
+
+    fn try_from(url: OidcAuthorizeUrl) -> Result<Self, TwitchOAuthError> {
+        url.build()
+    }
+}
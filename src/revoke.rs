@@ -0,0 +1,143 @@
+//! Token revocation
+
+use crate::client::{BatchSummary, TwitchOAuthClient};
+use crate::error::{read_body, TwitchApiError, TwitchOAuthError};
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use surf::RequestBuilder;
+use url::Url;
+
+/// What Twitch answered to a revocation that didn't fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevokeOutcome {
+    /// Twitch answered 200, the token can no longer be used
+    Revoked,
+    /// The token was already invalid, expired or revoked, so there is nothing left to revoke
+    AlreadyInvalid,
+    /// Twitch rejected the request with a 400 for another reason, e.g. an invalid client_id
+    BadRequest(String),
+}
+
+impl RevokeOutcome {
+    /// Whether the token can no longer be used, i.e. it was revoked now or before
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::RevokeOutcome;
+    ///
+    /// assert!(RevokeOutcome::Revoked.is_revoked());
+    /// assert!(RevokeOutcome::AlreadyInvalid.is_revoked());
+    /// assert!(!RevokeOutcome::BadRequest("invalid client_id".to_string()).is_revoked());
+    /// ```
+    pub fn is_revoked(&self) -> bool {
+        matches!(self, RevokeOutcome::Revoked | RevokeOutcome::AlreadyInvalid)
+    }
+}
+
+impl TwitchOAuthClient {
+    /// Revoke an access token issued to this client_id
+    ///
+    /// A 200 gives [`RevokeOutcome::Revoked`] and Twitch's `Invalid token` 400 gives
+    /// [`RevokeOutcome::AlreadyInvalid`], both meaning the token is unusable. Other 400s give
+    /// [`RevokeOutcome::BadRequest`], any other status fails with [`TwitchOAuthError::Api`].
+    pub async fn revoke(&self, access_token: &str) -> Result<RevokeOutcome, TwitchOAuthError> {
+        let mut params = HashMap::new();
+        params.insert("token", access_token);
+        params.insert("client_id", self.client_id.as_str());
+
+        let url = Url::parse_with_params(&self.endpoints.revoke, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let req: RequestBuilder = self.client.post(&url);
+        let mut res = self.send(req).await?;
+        if res.status() == surf::StatusCode::Ok {
+            return Ok(RevokeOutcome::Revoked);
+        }
+
+        let body = read_body(&mut res).await?;
+        let err = TwitchApiError::parse(res.status(), &body);
+        if res.status() != surf::StatusCode::BadRequest {
+            return Err(err.into());
+        }
+        if err.message.eq_ignore_ascii_case("invalid token") {
+            Ok(RevokeOutcome::AlreadyInvalid)
+        } else {
+            Ok(RevokeOutcome::BadRequest(err.message))
+        }
+    }
+
+    /// Revoke many access tokens concurrently, see [`TwitchOAuthClient::with_concurrency`]
+    ///
+    /// Results are returned in the order of `tokens`, a failure doesn't stop the other revocations.
+    /// Use [`TwitchOAuthClient::revoke_tokens_summary`] to also get a [`BatchSummary`] of them.
+    pub async fn revoke_tokens(
+        &self,
+        tokens: &[&str],
+    ) -> Vec<Result<RevokeOutcome, TwitchOAuthError>> {
+        stream::iter(tokens)
+            .map(|token| self.revoke(token))
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Revoke many access tokens like [`TwitchOAuthClient::revoke_tokens`], along with a [`BatchSummary`] of the results
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+    /// let revoked = client.revoke_tokens_summary(&["token1", "token2"]);
+    /// ```
+    pub async fn revoke_tokens_summary(
+        &self,
+        tokens: &[&str],
+    ) -> (Vec<Result<RevokeOutcome, TwitchOAuthError>>, BatchSummary) {
+        let results = self.revoke_tokens(tokens).await;
+        let summary = results.iter().collect();
+        (results, summary)
+    }
+}
+
+/// To revoke a token, you need to provide your access token and client_id
+///
+/// See [`TwitchOAuthClient::revoke`] for how Twitch's answers map to a [`RevokeOutcome`].
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::revoke_token("token", "client_id");
+/// ```
+pub async fn revoke_token(
+    access_token: &str,
+    client_id: &str,
+) -> Result<RevokeOutcome, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, "")
+        .revoke(access_token)
+        .await
+}
+
+/// To revoke many tokens at once, you need to provide the access tokens and client_id
+///
+/// ```rust
+/// let results = twitch_oauth_async_std::revoke_tokens(&["token1", "token2"], "client_id");
+/// ```
+pub async fn revoke_tokens(
+    access_tokens: &[&str],
+    client_id: &str,
+) -> Vec<Result<RevokeOutcome, TwitchOAuthError>> {
+    TwitchOAuthClient::new(client_id, "")
+        .revoke_tokens(access_tokens)
+        .await
+}
+
+/// Misspelled alias of [`revoke_token`], still returning the HTTP status of the revocation
+#[deprecated(note = "use revoke_token")]
+pub async fn remoke_token(
+    access_token: &str,
+    client_id: &str,
+) -> Result<surf::StatusCode, TwitchOAuthError> {
+    match revoke_token(access_token, client_id).await {
+        Ok(RevokeOutcome::Revoked) => Ok(surf::StatusCode::Ok),
+        Ok(_) => Ok(surf::StatusCode::BadRequest),
+        Err(TwitchOAuthError::Api { status, message }) => surf::StatusCode::try_from(status)
+            .map_err(|_| TwitchOAuthError::Api { status, message }),
+        Err(e) => Err(e),
+    }
+}
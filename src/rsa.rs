@@ -0,0 +1,14 @@
+//! RSASSA-PKCS1-v1_5 with SHA-256 signature verification, used for RS256 id_tokens
+//!
+//! The verification itself is done by `ring`, only keys of 2048 to 8192 bits are accepted.
+
+use ring::signature::{RsaPublicKeyComponents, RSA_PKCS1_2048_8192_SHA256};
+
+/// Verify `signature` over `message` with the public key (`n`, `e`), both big endian
+pub(crate) fn verify_rs256(n: &[u8], e: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    // ring rejects a modulus with leading zeros, which a JWK may still carry
+    let start = n.iter().position(|b| *b != 0).unwrap_or(n.len());
+    RsaPublicKeyComponents { n: &n[start..], e }
+        .verify(&RSA_PKCS1_2048_8192_SHA256, message, signature)
+        .is_ok()
+}
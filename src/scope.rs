@@ -0,0 +1,258 @@
+//! The scopes documented by Twitch
+
+use std::fmt;
+use std::ops::Deref;
+
+/// Split already joined scopes on whitespace, drop empty ones and duplicates, keeping the order
+pub(crate) fn normalize_scopes<S: AsRef<str>>(scopes: &[S]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for scope in scopes
+        .iter()
+        .flat_map(|scope| scope.as_ref().split_whitespace())
+    {
+        if !normalized.iter().any(|s| s == scope) {
+            normalized.push(scope.to_string());
+        }
+    }
+    normalized
+}
+
+// Scopes as Twitch sends them in a callback or a token response, joined by spaces
+pub(crate) fn split_scopes(scopes: &str) -> Vec<String> {
+    normalize_scopes(&[scopes])
+}
+
+pub(crate) fn join_scopes<S: AsRef<str>>(scopes: &[S]) -> String {
+    normalize_scopes(scopes).join(" ")
+}
+
+macro_rules! scopes {
+    ($($variant:ident => $name:literal,)*) => {
+        /// A scope documented by Twitch, use the string based functions for scopes not listed here
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Scope {
+            $(
+                #[doc = $name]
+                $variant,
+            )*
+        }
+
+        impl Scope {
+            /// Every known scope
+            pub const ALL: &'static [Scope] = &[$(Scope::$variant,)*];
+
+            /// The scope as Twitch spells it
+            ///
+            /// ```rust
+            /// use twitch_oauth_async_std::Scope;
+            ///
+            /// assert_eq!(Scope::ChannelReadSubscriptions.as_str(), "channel:read:subscriptions");
+            /// assert_eq!("chat:read".parse::<Scope>().unwrap(), Scope::ChatRead);
+            /// assert!("channel:read:subscritions".parse::<Scope>().is_err());
+            /// ```
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Scope::$variant => $name,)*
+                }
+            }
+        }
+
+        impl std::str::FromStr for Scope {
+            type Err = UnknownScope;
+
+            fn from_str(s: &str) -> Result<Scope, UnknownScope> {
+                match s {
+                    $($name => Ok(Scope::$variant),)*
+                    _ => Err(UnknownScope(s.to_string())),
+                }
+            }
+        }
+    };
+}
+
+scopes! {
+    AnalyticsReadExtensions => "analytics:read:extensions",
+    AnalyticsReadGames => "analytics:read:games",
+    BitsRead => "bits:read",
+    ChannelEditCommercial => "channel:edit:commercial",
+    ChannelManageBroadcast => "channel:manage:broadcast",
+    ChannelManageExtensions => "channel:manage:extensions",
+    ChannelManagePolls => "channel:manage:polls",
+    ChannelManagePredictions => "channel:manage:predictions",
+    ChannelManageRedemptions => "channel:manage:redemptions",
+    ChannelManageSchedule => "channel:manage:schedule",
+    ChannelManageVideos => "channel:manage:videos",
+    ChannelModerate => "channel:moderate",
+    ChannelReadEditors => "channel:read:editors",
+    ChannelReadGoals => "channel:read:goals",
+    ChannelReadHypeTrain => "channel:read:hype_train",
+    ChannelReadPolls => "channel:read:polls",
+    ChannelReadPredictions => "channel:read:predictions",
+    ChannelReadRedemptions => "channel:read:redemptions",
+    ChannelReadStreamKey => "channel:read:stream_key",
+    ChannelReadSubscriptions => "channel:read:subscriptions",
+    ChatEdit => "chat:edit",
+    ChatRead => "chat:read",
+    ClipsEdit => "clips:edit",
+    ModerationRead => "moderation:read",
+    ModeratorManageAutomod => "moderator:manage:automod",
+    ModeratorManageAutomodSettings => "moderator:manage:automod_settings",
+    ModeratorManageBannedUsers => "moderator:manage:banned_users",
+    ModeratorManageBlockedTerms => "moderator:manage:blocked_terms",
+    ModeratorManageChatSettings => "moderator:manage:chat_settings",
+    ModeratorReadAutomodSettings => "moderator:read:automod_settings",
+    ModeratorReadBlockedTerms => "moderator:read:blocked_terms",
+    ModeratorReadChatSettings => "moderator:read:chat_settings",
+    Openid => "openid",
+    UserEdit => "user:edit",
+    UserEditFollows => "user:edit:follows",
+    UserManageBlockedUsers => "user:manage:blocked_users",
+    UserReadBlockedUsers => "user:read:blocked_users",
+    UserReadBroadcast => "user:read:broadcast",
+    UserReadEmail => "user:read:email",
+    UserReadFollows => "user:read:follows",
+    UserReadSubscriptions => "user:read:subscriptions",
+    WhispersEdit => "whispers:edit",
+    WhispersRead => "whispers:read",
+}
+
+impl Scope {
+    /// To parse joined scopes, you need to provide them separated by whitespace
+    ///
+    /// Returns the known scopes and, separately, the ones not known to [`Scope`], both without
+    /// duplicates and in order.
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::Scope;
+    ///
+    /// let (known, unknown) = Scope::parse_list("chat:read  new:scope chat:edit chat:read");
+    /// assert_eq!(known, vec![Scope::ChatRead, Scope::ChatEdit]);
+    /// assert_eq!(unknown, vec!["new:scope".to_string()]);
+    /// ```
+    pub fn parse_list(scopes: &str) -> (Vec<Scope>, Vec<String>) {
+        let mut known = Vec::new();
+        let mut unknown = Vec::new();
+        for scope in split_scopes(scopes) {
+            match scope.parse() {
+                Ok(scope) => known.push(scope),
+                Err(UnknownScope(scope)) => unknown.push(scope),
+            }
+        }
+        (known, unknown)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Returned when parsing a scope that isn't a known [`Scope`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownScope(pub String);
+
+impl fmt::Display for UnknownScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown scope: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownScope {}
+
+/// An ordered set of [`Scope`]s without duplicates, the canonical way to pass typed scopes around
+///
+/// It derefs to `[Scope]`, so `&ScopeSet` can be passed to every function taking typed scopes.
+///
+/// ```rust
+/// use twitch_oauth_async_std::{Scope, ScopeSet};
+///
+/// let mut scopes = ScopeSet::parse("chat:read chat:edit chat:read").unwrap();
+/// assert_eq!(scopes.len(), 2);
+/// assert!(!scopes.insert(Scope::ChatEdit));
+/// assert!(scopes.insert(Scope::UserReadEmail));
+/// assert!(scopes.contains(Scope::UserReadEmail));
+/// assert_eq!(scopes.to_space_string(), "chat:read chat:edit user:read:email");
+///
+/// let unknown = ScopeSet::parse("chat:read new:scope other:scope").unwrap_err();
+/// assert_eq!(unknown.len(), 2);
+///
+/// # #[cfg(feature = "client")]
+/// let token = twitch_oauth_async_std::get_app_access_token_with_typed_scopes("client_id", "client_secret", &scopes);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScopeSet(Vec<Scope>);
+
+impl ScopeSet {
+    /// An empty set
+    pub fn new() -> ScopeSet {
+        ScopeSet::default()
+    }
+
+    /// To build a set from joined scopes, you need to provide them separated by whitespace
+    ///
+    /// Every scope not known to [`Scope`] is returned in the error.
+    pub fn parse(scopes: &str) -> Result<ScopeSet, Vec<UnknownScope>> {
+        let (known, unknown) = Scope::parse_list(scopes);
+        if unknown.is_empty() {
+            Ok(known.into_iter().collect())
+        } else {
+            Err(unknown.into_iter().map(UnknownScope).collect())
+        }
+    }
+
+    /// Add `scope`, returning whether it wasn't in the set yet
+    pub fn insert(&mut self, scope: Scope) -> bool {
+        if self.contains(scope) {
+            return false;
+        }
+        self.0.push(scope);
+        true
+    }
+
+    /// Whether `scope` is in the set
+    pub fn contains(&self, scope: Scope) -> bool {
+        self.0.contains(&scope)
+    }
+
+    /// The scopes joined by spaces, as Twitch expects them
+    pub fn to_space_string(&self) -> String {
+        self.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl Deref for ScopeSet {
+    type Target = [Scope];
+
+    fn deref(&self) -> &[Scope] {
+        &self.0
+    }
+}
+
+impl std::iter::FromIterator<Scope> for ScopeSet {
+    fn from_iter<I: IntoIterator<Item = Scope>>(iter: I) -> ScopeSet {
+        let mut set = ScopeSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Scope> for ScopeSet {
+    fn extend<I: IntoIterator<Item = Scope>>(&mut self, iter: I) {
+        for scope in iter {
+            self.insert(scope);
+        }
+    }
+}
+
+impl From<&[Scope]> for ScopeSet {
+    fn from(scopes: &[Scope]) -> ScopeSet {
+        scopes.iter().copied().collect()
+    }
+}
+
+impl fmt::Display for ScopeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_space_string())
+    }
+}
@@ -0,0 +1,136 @@
+//! The authorization code flow from authorize url to token, in one type
+
+use crate::authorize::{
+    generate_state, parse_callback, verify_state, AuthorizeOptions, CallbackError, Pkce,
+};
+use crate::client::TwitchOAuthClient;
+use crate::error::TwitchOAuthError;
+use crate::scope::{Scope, ScopeSet};
+use crate::user_token::UserAccessToken;
+use std::fmt;
+use url::Url;
+
+/// Errors returned while completing an [`AuthSession`]
+#[derive(Debug)]
+pub enum AuthSessionError {
+    /// Twitch redirected with an error or the callback is missing a parameter
+    Callback(CallbackError),
+    /// The state of the callback isn't the one of the session, e.g. a forged callback
+    StateMismatch,
+    /// The code could not be exchanged
+    Request(TwitchOAuthError),
+}
+
+impl fmt::Display for AuthSessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthSessionError::Callback(e) => write!(f, "{}", e),
+            AuthSessionError::StateMismatch => write!(f, "state mismatch"),
+            AuthSessionError::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuthSessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AuthSessionError::Callback(e) => Some(e),
+            AuthSessionError::Request(e) => Some(e),
+            AuthSessionError::StateMismatch => None,
+        }
+    }
+}
+
+impl From<CallbackError> for AuthSessionError {
+    fn from(e: CallbackError) -> Self {
+        AuthSessionError::Callback(e)
+    }
+}
+
+impl From<TwitchOAuthError> for AuthSessionError {
+    fn from(e: TwitchOAuthError) -> Self {
+        AuthSessionError::Request(e)
+    }
+}
+
+/// One login through the authorization code flow, with its state and PKCE
+///
+/// Keep the session, e.g. keyed by [`AuthSession::state`], between sending the user to
+/// [`AuthSession::authorize_url`] and Twitch redirecting them back.
+///
+/// ```rust
+/// use twitch_oauth_async_std::{AuthSession, Scope, TwitchOAuthClient};
+///
+/// let client = TwitchOAuthClient::new("client_id", "client_secret");
+/// let session = AuthSession::begin(client, "http://localhost:3000/callback", &[Scope::ChatRead]);
+///
+/// let url = session.authorize_url().unwrap();
+/// let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+/// assert_eq!(params["state"], session.state());
+/// assert_eq!(params["code_challenge_method"], "S256");
+///
+/// // Later, in the handler of the redirect
+/// let callback = url::Url::parse("http://localhost:3000/callback?code=abc&scope=chat%3Aread&state=forged").unwrap();
+/// let token = session.complete(&callback);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthSession {
+    client: TwitchOAuthClient,
+    options: AuthorizeOptions,
+    pkce: Pkce,
+}
+
+impl AuthSession {
+    /// To start a login, you need to provide the client, the redirect_uri and the scopes to request
+    ///
+    /// A random state and PKCE are generated for the session.
+    pub fn begin(client: TwitchOAuthClient, redirect_uri: &str, scopes: &[Scope]) -> AuthSession {
+        let pkce = Pkce::generate();
+        let options = AuthorizeOptions {
+            client_id: client.client_id().to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes: ScopeSet::from(scopes),
+            state: generate_state(),
+            pkce: Some(pkce.clone()),
+            ..Default::default()
+        };
+
+        AuthSession {
+            client,
+            options,
+            pkce,
+        }
+    }
+
+    /// The state sent to Twitch, to find the session again when the callback arrives
+    pub fn state(&self) -> &str {
+        &self.options.state
+    }
+
+    /// The url to send the user to, against the authorize endpoint of the client
+    pub fn authorize_url(&self) -> Result<Url, TwitchOAuthError> {
+        self.client.authorize_url(&self.options)
+    }
+
+    /// Verify the state of the url Twitch redirected to and exchange its code
+    ///
+    /// A callback with another state fails with [`AuthSessionError::StateMismatch`] without
+    /// exchanging the code.
+    pub async fn complete(&self, callback_url: &Url) -> Result<UserAccessToken, AuthSessionError> {
+        let callback = parse_callback(callback_url)?;
+        if !verify_state(&self.options.state, &callback.state) {
+            return Err(AuthSessionError::StateMismatch);
+        }
+
+        let token = self
+            .client
+            .exchange_code_pkce(
+                &callback.code,
+                &self.options.redirect_uri,
+                &self.pkce.code_verifier,
+            )
+            .await?;
+
+        Ok(token)
+    }
+}
@@ -0,0 +1,171 @@
+//! A token representation for persisting, with an absolute expiry
+
+use crate::app_token::AppAccessToken;
+use crate::token::{expires_within, DebugWith, Secret, DEFAULT_EXPIRY_SKEW};
+use crate::user_token::UserAccessToken;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// An app or user access token as it should be written to disk
+///
+/// The live types only know how long the token was valid for when obtained, which means nothing
+/// after a restart. This one records when it expires instead, as RFC3339 to the second.
+/// Converting back to a live type recomputes `expires_in` against the current clock.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use twitch_oauth_async_std::{StoredToken, UserAccessToken};
+///
+/// let token: UserAccessToken = serde_json::from_str(
+///     r#"{"access_token":"a","refresh_token":"r","expires_in":3600,"scope":["chat:read"],"token_type":"bearer"}"#,
+/// ).unwrap();
+/// let json = serde_json::to_string(&StoredToken::from(token)).unwrap();
+/// assert!(json.contains(r#""expires_at":""#));
+///
+/// let stored: StoredToken = serde_json::from_str(&json).unwrap();
+/// assert!(!stored.is_expired());
+/// let token = UserAccessToken::from(stored);
+/// assert_eq!(token.refresh_token, "r");
+/// assert!(token.expires_in_duration() > Duration::from_secs(3590));
+///
+/// let expired: StoredToken = serde_json::from_str(
+///     r#"{"access_token":"a","expires_at":"2020-01-01T00:00:00Z","scope":[],"token_type":"bearer"}"#,
+/// ).unwrap();
+/// assert!(expired.is_expired());
+/// assert_eq!(UserAccessToken::from(expired).expires_in, 0);
+/// ```
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct StoredToken {
+    pub access_token: String,
+    /// Only set for user access tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub expires_at: SystemTime,
+    #[serde(default)]
+    pub scope: Vec<String>,
+    pub token_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl StoredToken {
+    /// Whether the token is expired, against the current clock and allowing for 30 seconds of clock skew
+    ///
+    /// The skew is always the default one, whatever the `with_expiry_skew` of the client.
+    pub fn is_expired(&self) -> bool {
+        expires_within(
+            self.expires_at,
+            SystemTime::now(),
+            Duration::from_secs(0),
+            DEFAULT_EXPIRY_SKEW,
+        )
+    }
+
+    /// The `Debug` output with the tokens in full, which `{:?}` redacts
+    pub fn debug_full(&self) -> String {
+        format!("{:?}", DebugWith(|f| self.fmt_debug(f, true)))
+    }
+
+    // `expires_in` of a live token, as if it was obtained now
+    fn expires_in(&self) -> usize {
+        self.expires_at
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0))
+            .as_secs() as usize
+    }
+
+    fn fmt_debug(&self, f: &mut fmt::Formatter, full: bool) -> fmt::Result {
+        let secret = |value| Secret { value, full };
+        f.debug_struct("StoredToken")
+            .field("access_token", &secret(&self.access_token))
+            .field("refresh_token", &self.refresh_token.as_deref().map(secret))
+            .field("expires_at", &self.expires_at)
+            .field("scope", &self.scope)
+            .field("token_type", &self.token_type)
+            .field("id_token", &self.id_token.as_deref().map(secret))
+            .finish()
+    }
+}
+
+impl fmt::Debug for StoredToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_debug(f, false)
+    }
+}
+
+impl From<AppAccessToken> for StoredToken {
+    fn from(token: AppAccessToken) -> StoredToken {
+        StoredToken {
+            expires_at: token.expires_at(),
+            access_token: token.access_token,
+            refresh_token: None,
+            scope: token.scope.unwrap_or_default(),
+            token_type: token.token_type,
+            id_token: None,
+        }
+    }
+}
+
+impl From<UserAccessToken> for StoredToken {
+    fn from(token: UserAccessToken) -> StoredToken {
+        StoredToken {
+            expires_at: token.expires_at(),
+            access_token: token.access_token,
+            refresh_token: Some(token.refresh_token),
+            scope: token.scope,
+            token_type: token.token_type,
+            id_token: token.id_token,
+        }
+    }
+}
+
+impl From<StoredToken> for AppAccessToken {
+    fn from(token: StoredToken) -> AppAccessToken {
+        AppAccessToken {
+            expires_in: token.expires_in(),
+            access_token: token.access_token,
+            scope: Some(token.scope).filter(|scope| !scope.is_empty()),
+            token_type: token.token_type,
+            obtained_at: SystemTime::now(),
+        }
+    }
+}
+
+/// A token stored without a refresh token, i.e. an app access token, gets an empty one
+impl From<StoredToken> for UserAccessToken {
+    fn from(token: StoredToken) -> UserAccessToken {
+        UserAccessToken {
+            expires_in: token.expires_in(),
+            access_token: token.access_token,
+            refresh_token: token.refresh_token.unwrap_or_default(),
+            scope: token.scope,
+            token_type: token.token_type,
+            id_token: token.id_token,
+            obtained_at: SystemTime::now(),
+        }
+    }
+}
+
+mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::SystemTime;
+    use time::{Format, OffsetDateTime};
+
+    pub(super) fn serialize<S: Serializer>(
+        time: &SystemTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&OffsetDateTime::from(*time).format(Format::Rfc3339))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SystemTime, D::Error> {
+        let time = String::deserialize(deserializer)?;
+        OffsetDateTime::parse(&time, Format::Rfc3339)
+            .map(SystemTime::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,76 @@
+//! Helpers shared by the token types
+
+use serde::Deserialize;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+pub(crate) fn bearer_header(access_token: &str) -> String {
+    format!("Bearer {}", access_token)
+}
+
+pub(crate) fn helix_headers(access_token: &str, client_id: &str) -> Vec<(String, String)> {
+    vec![
+        ("Client-Id".to_string(), client_id.to_string()),
+        ("Authorization".to_string(), bearer_header(access_token)),
+    ]
+}
+
+// Twitch only issues bearer tokens, spelled `bearer`
+pub(crate) fn deserialize_bearer<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let token_type = String::deserialize(deserializer)?;
+    if !token_type.eq_ignore_ascii_case("bearer") {
+        return Err(serde::de::Error::custom(format!(
+            "expected bearer token_type, got {}",
+            token_type
+        )));
+    }
+    Ok(token_type)
+}
+
+pub(crate) fn expires_in_duration(expires_in: usize) -> Duration {
+    Duration::from_secs(expires_in as u64)
+}
+
+pub(crate) fn expires_at(obtained_at: SystemTime, expires_in: usize) -> SystemTime {
+    obtained_at + expires_in_duration(expires_in)
+}
+
+// A clock slightly behind Twitch's would otherwise see a token as valid right until Twitch rejects it
+pub(crate) const DEFAULT_EXPIRY_SKEW: Duration = Duration::from_secs(30);
+
+pub(crate) fn expires_within(
+    expires_at: SystemTime,
+    now: SystemTime,
+    duration: Duration,
+    skew: Duration,
+) -> bool {
+    now + duration + skew >= expires_at
+}
+
+/// A secret shown in `Debug` output, keeping only its first characters unless `full`
+pub(crate) struct Secret<'a> {
+    pub(crate) value: &'a str,
+    pub(crate) full: bool,
+}
+
+impl fmt::Debug for Secret<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.full {
+            write!(f, "{:?}", self.value)
+        } else {
+            let prefix: String = self.value.chars().take(2).collect();
+            write!(f, "\"{}...redacted\"", prefix)
+        }
+    }
+}
+
+/// Formats with the given closure, to get a `Debug` output other than the `Debug` impl
+pub(crate) struct DebugWith<F: Fn(&mut fmt::Formatter) -> fmt::Result>(pub(crate) F);
+
+impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Debug for DebugWith<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.0)(f)
+    }
+}
@@ -0,0 +1,667 @@
+//! User access tokens from the authorization code flow, their refresh and storage
+
+#[cfg(feature = "client")]
+use crate::client::TwitchOAuthClient;
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "client")]
+use crate::error::{read_json, TwitchOAuthError};
+use crate::token::{
+    bearer_header, deserialize_bearer, expires_at, expires_in_duration, expires_within,
+    helix_headers, DebugWith, Secret, DEFAULT_EXPIRY_SKEW,
+};
+#[cfg(feature = "client")]
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "client")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "client")]
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "client")]
+use std::future::Future;
+#[cfg(feature = "client")]
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "client")]
+use url::Url;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct UserAccessToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: usize,
+    pub scope: Vec<String>,
+    #[serde(deserialize_with = "deserialize_bearer")]
+    pub token_type: String,
+    /// The OpenID Connect id_token, present when the `openid` scope was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    /// When the token was obtained, set when the response is received
+    #[serde(default = "SystemTime::now")]
+    pub obtained_at: SystemTime,
+}
+
+impl UserAccessToken {
+    /// When the token expires
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let token: twitch_oauth_async_std::UserAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","refresh_token":"r","expires_in":3600,"scope":[],"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// assert!(!token.is_expired());
+    /// assert!(token.expires_within(Duration::from_secs(7200)));
+    /// ```
+    pub fn expires_at(&self) -> SystemTime {
+        expires_at(self.obtained_at, self.expires_in)
+    }
+
+    /// The `Authorization` header value for this token
+    pub fn bearer_header(&self) -> String {
+        bearer_header(&self.access_token)
+    }
+
+    /// The `Client-Id` and `Authorization` headers Helix expects with this token
+    pub fn helix_headers(&self, client_id: &str) -> Vec<(String, String)> {
+        helix_headers(&self.access_token, client_id)
+    }
+
+    /// How long the token was valid for when obtained
+    pub fn expires_in_duration(&self) -> Duration {
+        expires_in_duration(self.expires_in)
+    }
+
+    /// Whether the token is expired, treating it as expired 30 seconds early to allow for clock skew
+    ///
+    /// The skew is always the default one, the token doesn't know the
+    /// [`with_expiry_skew`](crate::TwitchOAuthClient::with_expiry_skew) of the client that
+    /// obtained it. Use [`expires_within_skewed`](Self::expires_within_skewed) for another skew.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_with(&SystemClock)
+    }
+
+    /// Whether the token expires within `duration`, allowing for 30 seconds of clock skew
+    ///
+    /// Like [`is_expired`](Self::is_expired), the skew is always the default one.
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        self.expires_within_with(&SystemClock, duration)
+    }
+
+    /// Whether the token is expired, against `clock` and allowing for 30 seconds of clock skew
+    pub fn is_expired_with(&self, clock: &dyn Clock) -> bool {
+        self.expires_within_with(clock, Duration::from_secs(0))
+    }
+
+    /// Whether the token expires within `duration`, against `clock` and allowing for 30 seconds of clock skew
+    pub fn expires_within_with(&self, clock: &dyn Clock, duration: Duration) -> bool {
+        self.expires_within_skewed(clock, duration, DEFAULT_EXPIRY_SKEW)
+    }
+
+    /// Whether the token expires within `duration`, against `clock` and treating it as expired `skew` early
+    ///
+    /// The only expiry check taking a skew, e.g. the one configured on a client, passed along with
+    /// its clock as `expires_within_skewed(client.clock(), duration, client.expiry_skew())`.
+    ///
+    /// ```rust
+    /// use std::time::{Duration, SystemTime};
+    /// use twitch_oauth_async_std::{MockClock, UserAccessToken};
+    ///
+    /// let clock = MockClock::new(SystemTime::now());
+    /// let mut token: UserAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"a","refresh_token":"r","expires_in":3600,"scope":[],"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// token.obtained_at = clock.now();
+    /// let no_time = Duration::from_secs(0);
+    /// assert!(!token.expires_within_skewed(&clock, no_time, Duration::from_secs(60)));
+    /// assert!(token.expires_within_skewed(&clock, no_time, Duration::from_secs(3600)));
+    /// ```
+    pub fn expires_within_skewed(
+        &self,
+        clock: &dyn Clock,
+        duration: Duration,
+        skew: Duration,
+    ) -> bool {
+        expires_within(self.expires_at(), clock.now(), duration, skew)
+    }
+}
+
+impl UserAccessToken {
+    /// The `Debug` output with the access, refresh and id tokens in full, which `{:?}` redacts
+    ///
+    /// ```rust
+    /// let token: twitch_oauth_async_std::UserAccessToken = serde_json::from_str(
+    ///     r#"{"access_token":"access","refresh_token":"refresh","expires_in":60,"scope":[],"token_type":"bearer"}"#,
+    /// ).unwrap();
+    /// let debug = format!("{:?}", token);
+    /// assert!(debug.contains(r#"access_token: "ac...redacted""#));
+    /// assert!(debug.contains(r#"refresh_token: "re...redacted""#));
+    /// assert!(token.debug_full().contains(r#"refresh_token: "refresh""#));
+    /// ```
+    pub fn debug_full(&self) -> String {
+        format!("{:?}", DebugWith(|f| self.fmt_debug(f, true)))
+    }
+
+    fn fmt_debug(&self, f: &mut fmt::Formatter, full: bool) -> fmt::Result {
+        let secret = |value| Secret { value, full };
+        f.debug_struct("UserAccessToken")
+            .field("access_token", &secret(&self.access_token))
+            .field("refresh_token", &secret(&self.refresh_token))
+            .field("expires_in", &self.expires_in)
+            .field("scope", &self.scope)
+            .field("token_type", &self.token_type)
+            .field("id_token", &self.id_token.as_deref().map(secret))
+            .field("obtained_at", &self.obtained_at)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UserAccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_debug(f, false)
+    }
+}
+
+// To use the `{}` marker, the trait `fmt::Display` must be implemented
+// manually for the type.
+impl fmt::Display for UserAccessToken {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string1 = format!("access_token: {}\n", self.access_token);
+        let string2 = format!("{} refresh_token: {}\n", string1, self.refresh_token);
+        let string3 = format!("{} expires_in: {}\n", string2, self.expires_in);
+        let string4 = format!("{} scope: {:?}\n", string3, self.scope);
+        let string5 = format!("{} token_type: {}\n", string4, self.token_type);
+        write!(f, "{}", string5)
+    }
+}
+
+/// Persists user access tokens, so a [`UserTokenManager`] survives restarts
+///
+/// Save a [`StoredToken`](crate::StoredToken) rather than the token itself, so its expiry
+/// survives the restart:
+///
+/// ```rust
+/// use twitch_oauth_async_std::{async_trait, StoredToken, TokenStore, UserAccessToken};
+///
+/// struct FileStore;
+///
+/// #[async_trait]
+/// impl TokenStore for FileStore {
+///     async fn load(&self) -> Option<UserAccessToken> {
+///         let json = async_std::fs::read_to_string("token.json").await.ok()?;
+///         serde_json::from_str::<StoredToken>(&json).ok().map(Into::into)
+///     }
+///
+///     async fn save(&self, token: &UserAccessToken) {
+///         let json = serde_json::to_string(&StoredToken::from(token.clone())).unwrap();
+///         let _ = async_std::fs::write("token.json", json).await;
+///     }
+/// }
+/// ```
+#[cfg(feature = "client")]
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// The stored token, if any
+    async fn load(&self) -> Option<UserAccessToken>;
+    /// Store `token`, replacing the previous one
+    async fn save(&self, token: &UserAccessToken);
+}
+
+/// A [`TokenStore`] keeping the token in memory
+#[cfg(feature = "client")]
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<UserAccessToken>>,
+}
+
+#[cfg(feature = "client")]
+impl MemoryTokenStore {
+    /// To create a store holding a token
+    pub fn with_token(token: UserAccessToken) -> MemoryTokenStore {
+        MemoryTokenStore {
+            token: Mutex::new(Some(token)),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self) -> Option<UserAccessToken> {
+        self.token.lock().unwrap().clone()
+    }
+
+    async fn save(&self, token: &UserAccessToken) {
+        *self.token.lock().unwrap() = Some(token.clone());
+    }
+}
+
+// A code and when it was first seen with which redirect_uri and verifier, with its token once exchanged
+#[cfg(feature = "client")]
+type ExchangedCodes = HashMap<String, (SystemTime, ExchangeOf, Option<UserAccessToken>)>;
+
+/// What a code was exchanged with, only the SHA-256 of the PKCE code_verifier is kept
+#[cfg(feature = "client")]
+#[derive(Clone, PartialEq)]
+struct ExchangeOf {
+    redirect_uri: String,
+    code_verifier: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "client")]
+impl ExchangeOf {
+    fn new(redirect_uri: &str, code_verifier: Option<&str>) -> ExchangeOf {
+        ExchangeOf {
+            redirect_uri: redirect_uri.to_string(),
+            code_verifier: code_verifier
+                .map(|verifier| Sha256::digest(verifier.as_bytes()).to_vec()),
+        }
+    }
+}
+
+/// Remembers recently exchanged codes, see `TwitchOAuthClient::with_code_exchange_guard`
+#[cfg(feature = "client")]
+#[derive(Clone)]
+pub(crate) struct CodeGuard {
+    ttl: Duration,
+    codes: Arc<Mutex<ExchangedCodes>>,
+}
+
+#[cfg(feature = "client")]
+impl fmt::Debug for CodeGuard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CodeGuard").field("ttl", &self.ttl).finish()
+    }
+}
+
+#[cfg(feature = "client")]
+impl CodeGuard {
+    pub(crate) fn new(ttl: Duration) -> CodeGuard {
+        CodeGuard {
+            ttl,
+            codes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Marks a code not seen yet as being exchanged. The token of a seen code is only handed back
+    // to the same redirect_uri and verifier, so a replayed code doesn't get around PKCE
+    fn begin<'a>(&'a self, code: &str, of: ExchangeOf, now: SystemTime) -> SeenCode<'a> {
+        let mut codes = self.codes.lock().unwrap();
+        let ttl = self.ttl;
+        codes.retain(|_, (seen_at, _, _)| now < *seen_at + ttl);
+
+        match codes.get(code) {
+            Some((_, seen_of, Some(token))) if *seen_of == of => SeenCode::Exchanged(token.clone()),
+            Some(_) => SeenCode::Taken,
+            None => {
+                codes.insert(code.to_string(), (now, of, None));
+                SeenCode::New(PendingCode {
+                    guard: self,
+                    code: code.to_string(),
+                    finished: false,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+enum SeenCode<'a> {
+    Exchanged(UserAccessToken),
+    // Being exchanged, or exchanged with another redirect_uri or verifier
+    Taken,
+    New(PendingCode<'a>),
+}
+
+/// A code being exchanged, forgotten when dropped before `finish` so a cancelled exchange can be retried
+#[cfg(feature = "client")]
+struct PendingCode<'a> {
+    guard: &'a CodeGuard,
+    code: String,
+    finished: bool,
+}
+
+#[cfg(feature = "client")]
+impl PendingCode<'_> {
+    // A failed exchange is forgotten too, so the code can be tried again
+    fn finish(mut self, result: &Result<UserAccessToken, TwitchOAuthError>, now: SystemTime) {
+        if let Ok(token) = result {
+            let mut codes = self.guard.codes.lock().unwrap();
+            if let Some(entry) = codes.get_mut(&self.code) {
+                *entry = (now, entry.1.clone(), Some(token.clone()));
+            }
+            self.finished = true;
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl Drop for PendingCode<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let mut codes = self.guard.codes.lock().unwrap();
+            if matches!(codes.get(&self.code), Some((_, _, None))) {
+                codes.remove(&self.code);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+type RefreshHook = Box<dyn Fn(&UserAccessToken) + Send + Sync>;
+
+/// Keeps a user access token valid, refreshing it when it expires
+///
+/// The token is loaded from the [`TokenStore`] on first use and saved after every refresh.
+/// Concurrent callers wait for a single in-flight refresh instead of refreshing again.
+///
+/// Dropping a call, e.g. on shutdown or timeout, leaves the manager usable: the token is only
+/// replaced once a refresh has completed.
+///
+/// ```rust
+/// # let token: twitch_oauth_async_std::UserAccessToken = serde_json::from_str(
+/// #     r#"{"access_token":"a","refresh_token":"r","expires_in":3600,"scope":[],"token_type":"bearer"}"#,
+/// # ).unwrap();
+/// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+/// let manager = twitch_oauth_async_std::UserTokenManager::new(client, token)
+///     .with_on_refresh(|token| println!("persist {}", token.refresh_token));
+/// let access_token = async_std::task::block_on(manager.valid_token()).unwrap();
+/// assert_eq!(access_token, "a");
+/// ```
+#[cfg(feature = "client")]
+pub struct UserTokenManager<S: TokenStore = MemoryTokenStore> {
+    client: TwitchOAuthClient,
+    store: S,
+    token: futures_util::lock::Mutex<Option<UserAccessToken>>,
+    on_refresh: Option<RefreshHook>,
+}
+
+#[cfg(feature = "client")]
+impl<S: TokenStore> fmt::Debug for UserTokenManager<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserTokenManager")
+            .field("client", &self.client)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+#[cfg(feature = "client")]
+impl UserTokenManager<MemoryTokenStore> {
+    /// To create a manager, you need to provide the client used to refresh and the current token
+    pub fn new(client: TwitchOAuthClient, token: UserAccessToken) -> UserTokenManager {
+        UserTokenManager::with_store(client, MemoryTokenStore::with_token(token))
+    }
+}
+
+#[cfg(feature = "client")]
+impl<S: TokenStore> UserTokenManager<S> {
+    /// To create a manager loading its token from `store`
+    pub fn with_store(client: TwitchOAuthClient, store: S) -> UserTokenManager<S> {
+        UserTokenManager {
+            client,
+            store,
+            token: futures_util::lock::Mutex::new(None),
+            on_refresh: None,
+        }
+    }
+
+    /// Call `hook` with the new token after every refresh, e.g. to persist the rotated refresh token
+    pub fn with_on_refresh<F>(mut self, hook: F) -> UserTokenManager<S>
+    where
+        F: Fn(&UserAccessToken) + Send + Sync + 'static,
+    {
+        self.on_refresh = Some(Box::new(hook));
+        self
+    }
+
+    /// The current token, without refreshing it
+    pub async fn token(&self) -> Option<UserAccessToken> {
+        let mut token = self.token.lock().await;
+        if token.is_none() {
+            *token = self.store.load().await;
+        }
+
+        token.clone()
+    }
+
+    /// A valid access token, refreshing it first if it is expired
+    ///
+    /// Fails with [`TwitchOAuthError::NoStoredToken`] when the store has no token.
+    pub async fn valid_token(&self) -> Result<String, TwitchOAuthError> {
+        let mut guard = self.token.lock().await;
+        if guard.is_none() {
+            *guard = self.store.load().await;
+        }
+        let token = guard.as_mut().ok_or(TwitchOAuthError::NoStoredToken)?;
+
+        if self.expires_within(token, Duration::from_secs(0)) {
+            self.refresh(token).await?;
+        }
+
+        Ok(token.access_token.clone())
+    }
+
+    /// The token, refreshed first only if it expires within `threshold`, and whether it was refreshed
+    ///
+    /// The token endpoint is rate limited, so this avoids refreshing a token valid for another hour.
+    /// Use the returned flag to decide whether the token needs to be persisted.
+    pub async fn refresh_if_expiring(
+        &self,
+        threshold: Duration,
+    ) -> Result<(UserAccessToken, bool), TwitchOAuthError> {
+        let mut guard = self.token.lock().await;
+        if guard.is_none() {
+            *guard = self.store.load().await;
+        }
+        let token = guard.as_mut().ok_or(TwitchOAuthError::NoStoredToken)?;
+
+        let refreshed = self.expires_within(token, threshold);
+        if refreshed {
+            self.refresh(token).await?;
+        }
+
+        Ok((token.clone(), refreshed))
+    }
+
+    /// A valid access token, validated with Twitch first and refreshed if Twitch rejects it
+    ///
+    /// Unlike [`UserTokenManager::valid_token`], this also catches tokens revoked before they expire.
+    /// Fails with the refresh error, e.g. [`TwitchOAuthError::InvalidRefreshToken`], when the
+    /// token can't be refreshed either.
+    pub async fn ensure_valid(&self) -> Result<String, TwitchOAuthError> {
+        let mut guard = self.token.lock().await;
+        if guard.is_none() {
+            *guard = self.store.load().await;
+        }
+        let token = guard.as_mut().ok_or(TwitchOAuthError::NoStoredToken)?;
+
+        match self.client.validate(&token.access_token).await {
+            Ok(_) => {}
+            Err(TwitchOAuthError::InvalidToken { .. }) => self.refresh(token).await?,
+            Err(e) => return Err(e),
+        }
+
+        Ok(token.access_token.clone())
+    }
+
+    fn expires_within(&self, token: &UserAccessToken, duration: Duration) -> bool {
+        token.expires_within_skewed(self.client.clock(), duration, self.client.expiry_skew())
+    }
+
+    // The new token is kept and handed to the hook before saving, so the rotated refresh token
+    // isn't lost if the caller is cancelled while the store saves
+    async fn refresh(&self, token: &mut UserAccessToken) -> Result<(), TwitchOAuthError> {
+        *token = self.client.refresh(&token.refresh_token).await?;
+        if let Some(hook) = &self.on_refresh {
+            hook(token);
+        }
+        self.store.save(token).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+impl TwitchOAuthClient {
+    /// Exchange an authorization code, the redirect_uri must be the one used to authorize
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<UserAccessToken, TwitchOAuthError> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "authorization_code");
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("client_secret", self.client_secret.as_str());
+        params.insert("code", code);
+        params.insert("redirect_uri", redirect_uri);
+
+        let of = ExchangeOf::new(redirect_uri, None);
+        self.exchange_guarded(code, of, self.post_code_exchange(params))
+            .await
+    }
+
+    /// Exchange an authorization code obtained with PKCE, sending the code_verifier instead of the client_secret
+    pub async fn exchange_code_pkce(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<UserAccessToken, TwitchOAuthError> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "authorization_code");
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("code", code);
+        params.insert("redirect_uri", redirect_uri);
+        params.insert("code_verifier", code_verifier);
+
+        let of = ExchangeOf::new(redirect_uri, Some(code_verifier));
+        self.exchange_guarded(code, of, self.post_code_exchange(params))
+            .await
+    }
+
+    async fn post_code_exchange(
+        &self,
+        params: HashMap<&str, &str>,
+    ) -> Result<UserAccessToken, TwitchOAuthError> {
+        let url = Url::parse_with_params(&self.endpoints.token, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let req = self.client.post(&url);
+        let mut res = self.send(req).await?;
+        let mut resp: UserAccessToken = read_json(&mut res).await?;
+        resp.obtained_at = self.clock().now();
+
+        Ok(resp)
+    }
+
+    // Only runs `exchange` if the code guard, when enabled, hasn't seen `code` yet
+    async fn exchange_guarded<F>(
+        &self,
+        code: &str,
+        of: ExchangeOf,
+        exchange: F,
+    ) -> Result<UserAccessToken, TwitchOAuthError>
+    where
+        F: Future<Output = Result<UserAccessToken, TwitchOAuthError>>,
+    {
+        let guard = match &self.code_guard {
+            Some(guard) => guard,
+            None => return exchange.await,
+        };
+        let pending = match guard.begin(code, of, self.clock().now()) {
+            SeenCode::Exchanged(token) => return Ok(token),
+            SeenCode::Taken => return Err(TwitchOAuthError::CodeAlreadyExchanged),
+            SeenCode::New(pending) => pending,
+        };
+
+        let result = exchange.await;
+        pending.finish(&result, self.clock().now());
+        result
+    }
+
+    /// Refresh a user access token
+    ///
+    /// Twitch may rotate the refresh token, so always store the `refresh_token` of the returned token.
+    /// A rejected refresh token is surfaced as [`TwitchOAuthError::InvalidRefreshToken`].
+    pub async fn refresh(&self, refresh_token: &str) -> Result<UserAccessToken, TwitchOAuthError> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "refresh_token");
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("client_secret", self.client_secret.as_str());
+        params.insert("refresh_token", refresh_token);
+        let url = Url::parse_with_params(&self.endpoints.token, &params)
+            .map_err(TwitchOAuthError::UrlBuild)?;
+
+        let req = self.client.post(&url);
+        let mut res = self.send(req).await?;
+        match read_json::<UserAccessToken>(&mut res).await {
+            Err(TwitchOAuthError::Api {
+                status: 400,
+                message,
+            }) if message.to_lowercase().contains("invalid refresh token") => {
+                Err(TwitchOAuthError::InvalidRefreshToken)
+            }
+            resp => resp.map(|mut token| {
+                token.obtained_at = self.clock().now();
+                token
+            }),
+        }
+    }
+}
+
+/// To exchange an authorization code, you need to provide your client_id, client_secret, the code and the same redirect_uri used to authorize
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::exchange_code("client_id", "client_secret", "code", "http://localhost:3000/callback");
+/// ```
+#[cfg(feature = "client")]
+pub async fn exchange_code(
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .exchange_code(code, redirect_uri)
+        .await
+}
+
+/// To refresh a token, you need to provide your client_id, client_secret and the refresh token
+///
+/// Twitch may rotate the refresh token, so always store the `refresh_token` of the returned token.
+/// A rejected refresh token is surfaced as [`TwitchOAuthError::InvalidRefreshToken`].
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::refresh_token("client_id", "client_secret", "refresh_token");
+/// ```
+#[cfg(feature = "client")]
+pub async fn refresh_token(
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, client_secret)
+        .refresh(refresh_token)
+        .await
+}
+
+/// To exchange an authorization code obtained with PKCE, you need to provide your client_id, the code, the redirect_uri and the code_verifier
+///
+/// ```rust
+/// let pkce = twitch_oauth_async_std::Pkce::generate();
+/// let token = twitch_oauth_async_std::exchange_code_pkce("client_id", "code", "http://localhost:3000/callback", &pkce.code_verifier);
+/// ```
+#[cfg(feature = "client")]
+pub async fn exchange_code_pkce(
+    client_id: &str,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<UserAccessToken, TwitchOAuthError> {
+    TwitchOAuthClient::new(client_id, "")
+        .exchange_code_pkce(code, redirect_uri, code_verifier)
+        .await
+}
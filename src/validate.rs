@@ -0,0 +1,421 @@
+//! Token validation
+
+#[cfg(feature = "client")]
+use crate::client::{BatchSummary, TwitchOAuthClient};
+#[cfg(feature = "client")]
+use crate::error::{invalid_token, read_json, TwitchOAuthError};
+use crate::scope::Scope;
+#[cfg(feature = "client")]
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+#[cfg(feature = "client")]
+use url::Url;
+
+/// The token information returned by the validate endpoint
+///
+/// ```rust
+/// let token: twitch_oauth_async_std::ValidatedToken = serde_json::from_str(
+///     r#"{"client_id":"id","login":"login","user_id":"1","scopes":["chat:read"],"expires_in":60}"#,
+/// ).unwrap();
+/// let display = token.to_string();
+/// assert!(display.contains("client_id: id"));
+/// assert!(display.contains("login: Some(\"login\")"));
+/// assert!(display.contains("user_id: Some(\"1\")"));
+/// assert!(display.contains("scopes: [\"chat:read\"]"));
+/// assert!(display.contains("expires_in: 60"));
+/// ```
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ValidatedToken {
+    pub client_id: String,
+    pub login: Option<String>,
+    pub user_id: Option<String>,
+    /// Empty when Twitch omits it, e.g. for some app tokens
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub expires_in: usize,
+}
+
+impl ValidatedToken {
+    /// The scopes known to [`Scope`], see [`ValidatedToken::unknown_scopes`] for the others
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{Scope, ValidatedToken};
+    ///
+    /// let token: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":null,"user_id":null,"scopes":["chat:read","new:scope"]}"#,
+    /// ).unwrap();
+    /// assert_eq!(token.parsed_scopes(), vec![Scope::ChatRead]);
+    /// assert_eq!(token.unknown_scopes(), vec!["new:scope".to_string()]);
+    /// ```
+    pub fn parsed_scopes(&self) -> Vec<Scope> {
+        self.scopes.iter().filter_map(|s| s.parse().ok()).collect()
+    }
+
+    /// The scopes not known to [`Scope`]
+    pub fn unknown_scopes(&self) -> Vec<String> {
+        self.scopes
+            .iter()
+            .filter(|s| s.parse::<Scope>().is_err())
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the token belongs to a user, i.e. Twitch returned a `user_id`
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::ValidatedToken;
+    ///
+    /// let user: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":"login","user_id":"1","scopes":[]}"#,
+    /// ).unwrap();
+    /// assert!(user.is_user_token());
+    /// assert!(!user.is_app_token());
+    ///
+    /// let app: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","expires_in":60}"#,
+    /// ).unwrap();
+    /// assert!(app.is_app_token());
+    /// assert!(app.scopes.is_empty());
+    /// ```
+    pub fn is_user_token(&self) -> bool {
+        self.user_id.is_some()
+    }
+
+    /// Whether the token is an app access token, without any user
+    pub fn is_app_token(&self) -> bool {
+        !self.is_user_token()
+    }
+
+    /// Whether the token was granted `scope`
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Whether the token was issued to the app identified by `client_id`
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::ValidatedToken;
+    ///
+    /// let token: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":null,"user_id":null,"scopes":[]}"#,
+    /// ).unwrap();
+    /// assert!(token.belongs_to("id"));
+    /// assert!(!token.belongs_to("other_id"));
+    /// ```
+    pub fn belongs_to(&self, client_id: &str) -> bool {
+        self.client_id == client_id
+    }
+
+    /// Check the token was granted every scope, listing the missing ones otherwise
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::ValidatedToken;
+    ///
+    /// let token: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":null,"user_id":null,"scopes":["chat:read"]}"#,
+    /// ).unwrap();
+    /// assert!(token.has_all_scopes(&["chat:read"]).is_ok());
+    /// let missing = token.has_all_scopes(&["chat:read", "chat:edit"]).unwrap_err();
+    /// assert_eq!(missing.0, vec!["chat:edit".to_string()]);
+    /// ```
+    pub fn has_all_scopes(&self, scopes: &[&str]) -> Result<(), MissingScopes> {
+        let missing: Vec<String> = scopes
+            .iter()
+            .filter(|scope| !self.has_scope(scope))
+            .map(|scope| scope.to_string())
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(MissingScopes(missing))
+        }
+    }
+
+    /// The typed scopes of `required` the token lacks, e.g. to only request those again
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::{Scope, ValidatedToken};
+    ///
+    /// let token: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":null,"user_id":null,"scopes":["chat:read"]}"#,
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     token.missing_scopes_for(&[Scope::ChatRead, Scope::ChatEdit]),
+    ///     vec![Scope::ChatEdit]
+    /// );
+    /// ```
+    pub fn missing_scopes_for(&self, required: &[Scope]) -> Vec<Scope> {
+        required
+            .iter()
+            .filter(|scope| !self.has_scope(scope.as_str()))
+            .copied()
+            .collect()
+    }
+
+    /// The scopes added and removed compared to `previous`, e.g. the scopes granted before a refresh
+    ///
+    /// A user can revoke scopes, which otherwise only shows up as 403s from Helix later.
+    ///
+    /// ```rust
+    /// use twitch_oauth_async_std::ValidatedToken;
+    ///
+    /// let token: ValidatedToken = serde_json::from_str(
+    ///     r#"{"client_id":"id","login":null,"user_id":null,"scopes":["chat:read","user:read:email"]}"#,
+    /// ).unwrap();
+    /// let diff = token.scope_diff(&["chat:read".to_string(), "chat:edit".to_string()]);
+    /// assert!(diff.is_reduced());
+    /// assert_eq!(diff.added, vec!["user:read:email".to_string()]);
+    /// assert_eq!(diff.removed, vec!["chat:edit".to_string()]);
+    /// ```
+    pub fn scope_diff(&self, previous: &[String]) -> ScopeDiff {
+        let added = self
+            .scopes
+            .iter()
+            .filter(|scope| !previous.contains(scope))
+            .cloned()
+            .collect();
+        let removed = previous
+            .iter()
+            .filter(|scope| !self.has_scope(scope))
+            .cloned()
+            .collect();
+
+        ScopeDiff { added, removed }
+    }
+}
+
+/// A [`ValidatedToken`] told apart by whether it belongs to a user, see [`TwitchOAuthClient::validate_typed`](crate::TwitchOAuthClient::validate_typed)
+///
+/// A token with a `login` but no `user_id`, or the other way around, is neither and fails to
+/// convert with [`IncompleteUser`].
+///
+/// ```rust
+/// use std::convert::TryFrom;
+/// use twitch_oauth_async_std::{ValidatedToken, ValidatedTokenKind};
+///
+/// let token: ValidatedToken = serde_json::from_str(
+///     r#"{"client_id":"id","login":"login","user_id":"1","scopes":["chat:read"],"expires_in":60}"#,
+/// ).unwrap();
+/// match ValidatedTokenKind::try_from(token).unwrap() {
+///     ValidatedTokenKind::User { login, user_id, .. } => assert_eq!((login.as_str(), user_id.as_str()), ("login", "1")),
+///     ValidatedTokenKind::App { .. } => panic!("expected a user token"),
+/// }
+///
+/// let token: ValidatedToken = serde_json::from_str(r#"{"client_id":"id","expires_in":60}"#).unwrap();
+/// assert!(matches!(ValidatedTokenKind::try_from(token), Ok(ValidatedTokenKind::App { .. })));
+///
+/// let token: ValidatedToken = serde_json::from_str(r#"{"client_id":"id","user_id":"1","expires_in":60}"#).unwrap();
+/// assert!(ValidatedTokenKind::try_from(token).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidatedTokenKind {
+    /// An app access token, without any user
+    App {
+        client_id: String,
+        scopes: Vec<String>,
+        expires_in: usize,
+    },
+    /// A user access token
+    User {
+        client_id: String,
+        login: String,
+        user_id: String,
+        scopes: Vec<String>,
+        expires_in: usize,
+    },
+}
+
+impl TryFrom<ValidatedToken> for ValidatedTokenKind {
+    type Error = IncompleteUser;
+
+    fn try_from(token: ValidatedToken) -> Result<Self, IncompleteUser> {
+        match (token.login, token.user_id) {
+            (Some(login), Some(user_id)) => Ok(ValidatedTokenKind::User {
+                client_id: token.client_id,
+                login,
+                user_id,
+                scopes: token.scopes,
+                expires_in: token.expires_in,
+            }),
+            (None, None) => Ok(ValidatedTokenKind::App {
+                client_id: token.client_id,
+                scopes: token.scopes,
+                expires_in: token.expires_in,
+            }),
+            (login, user_id) => Err(IncompleteUser { login, user_id }),
+        }
+    }
+}
+
+/// Returned when a [`ValidatedToken`] has only one of `login` and `user_id`, holding the one it has
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompleteUser {
+    pub login: Option<String>,
+    pub user_id: Option<String>,
+}
+
+impl fmt::Display for IncompleteUser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "validated token with login {:?} and user_id {:?}, expected both or neither",
+            self.login, self.user_id
+        )
+    }
+}
+
+impl std::error::Error for IncompleteUser {}
+
+/// Returned by [`ValidatedToken::scope_diff`] with the scopes gained and lost
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ScopeDiff {
+    /// Whether any previous scope was lost
+    pub fn is_reduced(&self) -> bool {
+        !self.removed.is_empty()
+    }
+
+    /// Whether the scopes are the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Returned by [`ValidatedToken::has_all_scopes`] with the scopes the token lacks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingScopes(pub Vec<String>);
+
+impl fmt::Display for MissingScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing scopes: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for MissingScopes {}
+
+// To use the `{}` marker, the trait `fmt::Display` must be implemented
+// manually for the type.
+impl fmt::Display for ValidatedToken {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string1 = format!("client_id: {}\n", self.client_id);
+        let string2 = format!("{} login: {:?}\n", string1, self.login);
+        let string3 = format!("{} user_id: {:?}\n", string2, self.user_id);
+        let string4 = format!("{} scopes: {:?}\n", string3, self.scopes);
+        let string5 = format!("{} expires_in: {}\n", string4, self.expires_in);
+
+        write!(f, "{}", string5)
+    }
+}
+
+#[cfg(feature = "client")]
+impl TwitchOAuthClient {
+    /// Validate an access token
+    ///
+    /// An invalid or expired token is surfaced as [`TwitchOAuthError::InvalidToken`].
+    pub async fn validate(&self, access_token: &str) -> Result<ValidatedToken, TwitchOAuthError> {
+        let auth = format!("OAuth {}", access_token);
+        let url = Url::parse(&self.endpoints.validate).map_err(TwitchOAuthError::UrlBuild)?;
+
+        let mut res = self
+            .send_with_retries(|| self.client.get(&url).header("authorization", auth.as_str()))
+            .await?;
+        if res.status() == surf::StatusCode::Unauthorized {
+            return Err(invalid_token(&res));
+        }
+        let resp: ValidatedToken = read_json(&mut res).await?;
+
+        Ok(resp)
+    }
+
+    /// Validate an access token, telling app and user tokens apart
+    ///
+    /// Fails with [`TwitchOAuthError::IncompleteUser`] when Twitch sends only one of `login` and `user_id`.
+    pub async fn validate_typed(
+        &self,
+        access_token: &str,
+    ) -> Result<ValidatedTokenKind, TwitchOAuthError> {
+        Ok(ValidatedTokenKind::try_from(
+            self.validate(access_token).await?,
+        )?)
+    }
+
+    /// Validate an access token and check it was issued to this client_id
+    ///
+    /// A token minted by another app is surfaced as [`TwitchOAuthError::ClientIdMismatch`].
+    pub async fn validate_for_app(
+        &self,
+        access_token: &str,
+    ) -> Result<ValidatedToken, TwitchOAuthError> {
+        let token = self.validate(access_token).await?;
+        if !token.belongs_to(&self.client_id) {
+            return Err(TwitchOAuthError::ClientIdMismatch {
+                expected: self.client_id.clone(),
+                actual: token.client_id,
+            });
+        }
+
+        Ok(token)
+    }
+
+    /// Validate many access tokens concurrently, see [`TwitchOAuthClient::with_concurrency`]
+    ///
+    /// Results are returned in the order of `tokens`, next to the token they belong to. Use
+    /// [`TwitchOAuthClient::validate_tokens_summary`] to also get a [`BatchSummary`] of them.
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret")
+    ///     .with_concurrency(4);
+    /// let tokens = vec!["token1".to_string(), "token2".to_string()];
+    /// let results = client.validate_tokens(&tokens);
+    /// ```
+    pub async fn validate_tokens(
+        &self,
+        tokens: &[String],
+    ) -> Vec<(String, Result<ValidatedToken, TwitchOAuthError>)> {
+        stream::iter(tokens)
+            .map(|token| async move { (token.clone(), self.validate(token).await) })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Validate many access tokens like [`TwitchOAuthClient::validate_tokens`], along with a [`BatchSummary`] of the results
+    ///
+    /// ```rust
+    /// let client = twitch_oauth_async_std::TwitchOAuthClient::new("client_id", "client_secret");
+    /// let tokens = vec!["token1".to_string(), "token2".to_string()];
+    /// let validated = client.validate_tokens_summary(&tokens);
+    /// ```
+    pub async fn validate_tokens_summary(
+        &self,
+        tokens: &[String],
+    ) -> (
+        Vec<(String, Result<ValidatedToken, TwitchOAuthError>)>,
+        BatchSummary,
+    ) {
+        let results = self.validate_tokens(tokens).await;
+        let summary = results.iter().map(|(_, result)| result).collect();
+        (results, summary)
+    }
+}
+
+/// To validate a token, you need to provide your access token
+///
+/// An invalid or expired token is surfaced as [`TwitchOAuthError::InvalidToken`].
+///
+/// ```rust
+/// let token = twitch_oauth_async_std::validate_token("access_token");
+/// ```
+#[cfg(feature = "client")]
+pub async fn validate_token(access_token: &str) -> Result<ValidatedToken, TwitchOAuthError> {
+    TwitchOAuthClient::new("", "").validate(access_token).await
+}